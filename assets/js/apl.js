@@ -29,23 +29,23 @@ export default {
         //PLAYER_MANA_DEFICIT: "PlayerManaDeficit",
         //PLAYER_TALENT_COUNT: "PlayerTalentCount",
         PLAYER_COOLDOWN_EXISTS: "PlayerCooldownExists",
-        //PLAYER_COOLDOWN_REACT: "PlayerCooldownReact",
+        PLAYER_COOLDOWN_REACT: "PlayerCooldownReact",
         PLAYER_COOLDOWN_DURATION: "PlayerCooldownDuration",
         PLAYER_AURA_EXISTS: "PlayerAuraExists",
-        //PLAYER_AURA_REACT: "PlayerAuraReact",
+        PLAYER_AURA_REACT: "PlayerAuraReact",
         //PLAYER_AURA_STACKS: "PlayerAuraStacks",
         PLAYER_AURA_DURATION: "PlayerAuraDuration",
 
         TARGET_AURA_EXISTS: "TargetAuraExists",
-        //TARGET_AURA_REACT: "TargetAuraReact",
+        TARGET_AURA_REACT: "TargetAuraReact",
         TARGET_AURA_STACKS: "TargetAuraStacks",
         TARGET_AURA_DURATION: "TargetAuraDuration",
 
-        //SPELL_TRAVEL_TIME: "SpellTravelTime",
-        //SPELL_CAST_TIME: "SpellCastTime",
-        //SPELL_TRAVEL_CAST_TIME: "SpellTravelCastTime",
+        SPELL_TRAVEL_TIME: "SpellTravelTime",
+        SPELL_CAST_TIME: "SpellCastTime",
+        SPELL_TRAVEL_CAST_TIME: "SpellTravelCastTime",
         //SPELL_MANA_COST: "SpellManaCost",
-        //SPELL_CAN_CAST: "SpellCanCast",
+        SPELL_CAN_CAST: "SpellCanCast",
 
         SIM_TIME: "SimTime",
         SIM_TIME_PERCENT: "SimTimePercent",
@@ -53,6 +53,14 @@ export default {
         //SIM_DISTANCE: "SimDistance",
         //SIM_REACTION_TIME: "SimReactionTime",
         //SIM_TARGET_LEVEL: "SimTargetLevel",
+
+        ADD: "Add",
+        SUB: "Sub",
+        MUL: "Mul",
+        DIV: "Div",
+        MIN: "Min",
+        MAX: "Max",
+        ABS: "Abs",
     },
 
     actions() {
@@ -157,6 +165,7 @@ export default {
             vstr: "",
             vint: 0,
             vfloat: 0,
+            values: [],
         }
     },
 
@@ -8,7 +8,10 @@ class SimContainer {
         this.iterations = parseInt(iterations);
         this.workers = [];
         this.runs = [];
+        this.completed_runs = 0;
         this.start_time = null;
+        this.cancelled = false;
+        this.chunks_by_raid = new Map(); // Chunk accumulators for each raid
         this.results_by_raid = new Map(); // Store results for each raid
 
         if (!this.threads || isNaN(this.threads))
@@ -19,20 +22,34 @@ class SimContainer {
         let run_itr = Math.max(50, Math.min(200, Math.ceil(this.iterations/this.threads)));
         let num_workers = Math.min(this.threads, Math.ceil(this.iterations/run_itr));
 
-        // Create runs for each config
+        // Create runs for each config. Runs cover consecutive iterations, so the merged
+        // chunks of a raid are the same fights as one big run
         for (let config of this.configs) {
+            let raid = {
+                config: config,
+                chunks: [],
+                runs: 0,
+            };
+            this.chunks_by_raid.set(config.raid_id, raid);
             for (let total = 0; total < this.iterations; total += run_itr) {
                 let itr = Math.min(run_itr, this.iterations - total);
                 this.runs.push({
+                    first_iteration: total + 1,
                     iterations: itr,
                     config: config,
                     raid_id: config.raid_id,
                     raid_name: config.raid_name,
                     is_active_raid: config.is_active_raid,
                     started: false,
+                    done: 0, // Iterations run so far
+                    dps: 0, // Partial DPS over those
                 });
+                raid.runs++;
             }
         }
+        // A raid run in one piece is finished by its session without a merge
+        for (let run of this.runs)
+            run.whole = this.chunks_by_raid.get(run.raid_id).runs == 1;
 
         for (let i = 0; i < num_workers; i++) {
             this.workers.push(new Worker());
@@ -40,66 +57,75 @@ class SimContainer {
             this.workers[i].onmessage = (event) => {
                 let data = event.data;
 
+                // Cancelled sessions have stopped, nothing else is reported
+                if (data.type == "cancelled") {
+                    this.workers[i].terminate();
+                    return;
+                }
+                if (this.cancelled)
+                    return;
+
                 if (data.type == "error") {
                     this.workers[i].terminate();
                     onError(data);
                 }
 
-                // Thread done
-                if (data.type == "success") {
-                    const raid_id = data.raid_id;
-                    
-                    // Initialize or merge results for this raid
-                    if (!this.results_by_raid.has(raid_id)) {
-                        this.results_by_raid.set(raid_id, {
-                            ...data.result,
-                            raid_id: raid_id,
-                            raid_name: data.raid_name,
-                            is_active_raid: data.is_active_raid,
-                            damage_log: data.result.damage_log || []
-                        });
-                    } else {
-                        this.mergeResults(raid_id, data.result);
-                    }
-
-                    // Check if all runs are complete
-                    const completedIterations = this.getTotalCompletedIterations();
-                    const totalExpectedIterations = this.iterations * this.configs.length;
+                // Session step done, report the converging numbers
+                if (data.type == "progress") {
+                    const run = this.runs.find(r => r.raid_id == data.raid_id && r.first_iteration == data.first_iteration);
+                    run.done = data.iterations;
+                    run.dps = data.dps;
 
                     if (onProgress) {
                         const progress = {
-                            iterations: Math.floor(completedIterations / this.configs.length),
+                            iterations: Math.floor(this.getTotalCompletedIterations() / this.configs.length),
                             dps: this.calculateAverageDps(),
                         };
                         onProgress(progress);
                     }
+                }
+
+                // Chunk done, merge the raid's chunks on this worker once they are all in
+                if (data.type == "chunk") {
+                    const raid = this.chunks_by_raid.get(data.raid_id);
+                    raid.chunks.push(data.chunk);
+                    this.completed_runs++;
+
+                    if (raid.chunks.length == raid.runs) {
+                        this.workers[i].postMessage({
+                            type: "merge",
+                            config: raid.config,
+                            chunks: raid.chunks,
+                            raid_id: raid.config.raid_id,
+                            raid_name: raid.config.raid_name,
+                            is_active_raid: raid.config.is_active_raid,
+                        });
+                    }
+                    else if (!this.startNextRun(i)) {
+                        this.workers[i].terminate();
+                    }
+                }
 
-                    // Special handling for single iteration
-                    if (this.iterations === 1) {
-                        // For single iteration, check if this specific raid is complete
-                        const raidResult = this.results_by_raid.get(raid_id);
-                        if (raidResult && raidResult.iterations === 1) {
-                            // Check if all raids are complete
-                            if (this.results_by_raid.size === this.configs.length) {
-                                this.workers[i].terminate();
-                                const finalResult = this.compileFinalResults();
-                                finalResult.time = (Date.now() - this.start_time) / 1000;
-                                onSuccess(finalResult);
-                            } else if (!this.startNextRun(i)) {
-                                this.workers[i].terminate();
-                            }
-                        }
-                    } else {
-                        // Multiple iterations handling
-                        if (completedIterations >= totalExpectedIterations) {
-                            this.workers[i].terminate();
-                            const finalResult = this.compileFinalResults();
-                            finalResult.time = (Date.now() - this.start_time) / 1000;
-                            onSuccess(finalResult);
-                        } else {
-                            if (!this.startNextRun(i))
-                                this.workers[i].terminate();
-                        }
+                // Raid done
+                if (data.type == "success") {
+                    const raid_id = data.raid_id;
+                    this.results_by_raid.set(raid_id, {
+                        ...data.result,
+                        raid_id: raid_id,
+                        raid_name: data.raid_name,
+                        is_active_raid: data.is_active_raid,
+                        damage_log: data.result.damage_log || []
+                    });
+
+                    if (this.results_by_raid.size === this.configs.length) {
+                        for (let worker of this.workers)
+                            worker.terminate();
+                        const finalResult = this.compileFinalResults();
+                        finalResult.time = (Date.now() - this.start_time) / 1000;
+                        onSuccess(finalResult);
+                    }
+                    else if (!this.startNextRun(i)) {
+                        this.workers[i].terminate();
                     }
                 }
             };
@@ -112,82 +138,29 @@ class SimContainer {
 
     }
 
-    mergeResults(raid_id, newResult) {
-        let sum = this.results_by_raid.get(raid_id);
-        
-        if (newResult.min_dps < sum.min_dps)
-            sum.min_dps = newResult.min_dps;
-        if (newResult.max_dps > sum.max_dps)
-            sum.max_dps = newResult.max_dps;
-            
-        sum.dps = (sum.dps * sum.iterations + newResult.dps * newResult.iterations) / 
-                  (sum.iterations + newResult.iterations);
-        sum.ignite_dps = (sum.ignite_dps * sum.iterations + newResult.ignite_dps * newResult.iterations) / 
-                         (sum.iterations + newResult.iterations);
-
-        // Merge histograms
-        if (newResult.histogram) {
-            if (!sum.histogram) sum.histogram = new Map();
-            for (const [key, val] of newResult.histogram) {
-                let acc = sum.histogram.get(key);
-                sum.histogram.set(key, val + (acc ? acc : 0));
-            }
-        }
-
-        // Merge player data
-        for (let j = 0; j < sum.players.length; j++) {
-            sum.players[j].dps = (sum.players[j].dps * sum.iterations + 
-                                 newResult.players[j].dps * newResult.iterations) / 
-                                 (sum.iterations + newResult.iterations);
-            sum.players[j].ignite_dps = (sum.players[j].ignite_dps * sum.iterations + 
-                                         newResult.players[j].ignite_dps * newResult.iterations) / 
-                                         (sum.iterations + newResult.iterations);
-        }
-
-        // Merge stat weights (only for active raid)
-        if (sum.is_active_raid && newResult.dps_sp) {
-            sum.dps_select = (sum.dps_select || 0) + newResult.dps_select;
-            sum.dps_sp = (sum.dps_sp || 0) + newResult.dps_sp;
-            sum.dps_crit = (sum.dps_crit || 0) + newResult.dps_crit;
-            sum.dps_hit = (sum.dps_hit || 0) + newResult.dps_hit;
-            sum.dps90_select = (sum.dps90_select || 0) + newResult.dps90_select;
-            sum.dps90_sp = (sum.dps90_sp || 0) + newResult.dps90_sp;
-            sum.dps90_crit = (sum.dps90_crit || 0) + newResult.dps90_crit;
-            sum.dps90_hit = (sum.dps90_hit || 0) + newResult.dps90_hit;
-        }
-
-        // Merge DPS timeline
-        if (newResult.damage_log) {
-            if (!sum.damage_log) sum.damage_log = [];
-            // Average the timelines
-            for (let i = 0; i < newResult.damage_log.length; i++) {
-                if (i < sum.damage_log.length) {
-                    sum.damage_log[i] = (sum.damage_log[i] * sum.iterations + 
-                                          newResult.damage_log[i] * newResult.iterations) / 
-                                          (sum.iterations + newResult.iterations);
-                } else {
-                    sum.damage_log[i] = newResult.damage_log[i];
-                }
-            }
-        }
-
-        sum.iterations += newResult.iterations;
-    }
-
     getTotalCompletedIterations() {
         let total = 0;
-        for (let result of this.results_by_raid.values()) {
-            total += result.iterations;
-        }
+        for (let run of this.runs)
+            total += run.done;
         return total;
     }
 
     calculateAverageDps() {
         let totalDps = 0;
         let count = 0;
-        for (let result of this.results_by_raid.values()) {
-            totalDps += result.dps;
-            count++;
+        for (let raid_id of this.chunks_by_raid.keys()) {
+            let sum = 0;
+            let n = 0;
+            for (let run of this.runs) {
+                if (run.raid_id == raid_id) {
+                    sum += run.dps * run.done;
+                    n += run.done;
+                }
+            }
+            if (n > 0) {
+                totalDps += sum / n;
+                count++;
+            }
         }
         return count > 0 ? totalDps / count : 0;
     }
@@ -233,6 +206,13 @@ class SimContainer {
         };
     }
 
+    // Ask every session to stop before its next step; no callback fires afterwards
+    cancel() {
+        this.cancelled = true;
+        for (let worker of this.workers)
+            worker.postMessage({ type: "cancel" });
+    }
+
     start() {
         this.start_time = Date.now();
         for (let i = 0; i < this.workers.length; i++) {
@@ -242,13 +222,14 @@ class SimContainer {
 
     startRun(worker_index, run_index) {
         let run = this.runs[run_index];
-        let config = _.cloneDeep(run.config);
-        config.rng_seed = config.rng_seed + run_index * run.iterations;
 
+        // A single iteration runs whole, with its combat log
         this.workers[worker_index].postMessage({
-            type: "start",
-            config: config,
+            type: this.iterations === 1 ? "start" : "session",
+            config: run.config,
+            first_iteration: run.first_iteration,
             iterations: run.iterations,
+            whole: run.whole,
             raid_id: run.raid_id,
             raid_name: run.raid_name,
             is_active_raid: run.is_active_raid,
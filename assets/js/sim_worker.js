@@ -1,5 +1,58 @@
 // sim_worker.js
-import init, { run_simulation, run_simulations } from "simulator";
+import init, { run_simulation, run_simulations, merge_simulation_chunks, SimSession } from "simulator";
+
+// Iterations per session step; progress is reported and cancel checked between steps
+const STEP_ITERATIONS = 20;
+
+let session = null;
+let cancelled = false;
+
+// Run one step of the session, then yield so a "cancel" message can get in before the next
+const step = (data) => {
+    if (cancelled) {
+        session.free();
+        session = null;
+        postMessage({ type: "cancelled" });
+        return;
+    }
+
+    session.run_chunk(STEP_ITERATIONS);
+    const partial = session.partial();
+    postMessage({
+        type: "progress",
+        raid_id: data.raid_id,
+        first_iteration: data.first_iteration,
+        iterations: session.progress(),
+        dps: partial.dps,
+    });
+
+    if (!session.is_done()) {
+        setTimeout(() => step(data), 0);
+        return;
+    }
+
+    // A raid run in one piece finishes here; otherwise its pieces are merged later
+    let message;
+    if (data.whole) {
+        message = {
+            type: "success",
+            raid_id: data.raid_id,
+            raid_name: data.raid_name,
+            is_active_raid: data.is_active_raid,
+            result: session.finish(),
+        };
+    }
+    else {
+        message = {
+            type: "chunk",
+            raid_id: data.raid_id,
+            chunk: session.accumulator(),
+        };
+        session.free();
+    }
+    session = null;
+    postMessage(message);
+};
 
 onmessage = (event) => {
 
@@ -26,4 +79,42 @@ onmessage = (event) => {
             console.warn(e);
         });
     }
+
+    // Run iterations [first_iteration, first_iteration + iterations) through a session. The mergeable
+    // accumulator it returns carries the stat weights paired with those iterations
+    if (event.data.type == "session") {
+        init().then(r => {
+            cancelled = false;
+            session = new SimSession(event.data.config, event.data.first_iteration, event.data.iterations);
+            step(event.data);
+        })
+        .catch(e => {
+            console.warn(e);
+        });
+    }
+
+    // Stop the running session before its next step
+    if (event.data.type == "cancel") {
+        if (session)
+            cancelled = true;
+        else
+            postMessage({ type: "cancelled" });
+    }
+
+    // Combine chunk accumulators into a final result
+    if (event.data.type == "merge") {
+        init().then(r => {
+            let result = merge_simulation_chunks(event.data.config, event.data.chunks);
+            postMessage({
+                type: "success",
+                raid_id: event.data.raid_id,
+                raid_name: event.data.raid_name,
+                is_active_raid: event.data.is_active_raid,
+                result: result,
+            });
+        })
+        .catch(e => {
+            console.warn(e);
+        });
+    }
 }
\ No newline at end of file
@@ -35,14 +35,28 @@ pub enum AplValueType {
     Const,
     PlayerCooldownExists,
     PlayerCooldownDuration,
+    PlayerCooldownReact,
     PlayerAuraExists,
+    PlayerAuraReact,
     PlayerAuraDuration,
     TargetAuraExists,
+    TargetAuraReact,
     TargetAuraStacks,
     TargetAuraDuration,
     SimTime,
     SimTimePercent,
     SimDuration,
+    SpellCastTime,
+    SpellTravelTime,
+    SpellTravelCastTime,
+    SpellCanCast,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Min,
+    Max,
+    Abs,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -70,6 +84,21 @@ pub struct AplValue {
     pub vstr: String,
     pub vfloat: f64,
     pub vint: i32,
+    pub values: Vec<AplValue>, // operands of arithmetic values
+}
+
+impl AplValue {
+    /// Reads a state with reaction time, here or in an operand
+    pub fn reads_reaction(&self) -> bool {
+        matches!(self.value_type, AplValueType::PlayerCooldownReact | AplValueType::PlayerAuraReact | AplValueType::TargetAuraReact)
+            || self.values.iter().any(AplValue::reads_reaction)
+    }
+}
+
+impl AplCondition {
+    pub fn reads_reaction(&self) -> bool {
+        self.values.iter().any(AplValue::reads_reaction) || self.conditions.iter().any(AplCondition::reads_reaction)
+    }
 }
 
 // Helper trait for parsing enums from strings
@@ -111,6 +140,9 @@ impl FromJsonString for AplValueType {
             "Const" => AplValueType::Const,
             "PlayerCooldownExists" => AplValueType::PlayerCooldownExists,
             "PlayerCooldownDuration" => AplValueType::PlayerCooldownDuration,
+            "PlayerCooldownReact" => AplValueType::PlayerCooldownReact,
+            "PlayerAuraReact" => AplValueType::PlayerAuraReact,
+            "TargetAuraReact" => AplValueType::TargetAuraReact,
             "PlayerAuraExists" => AplValueType::PlayerAuraExists,
             "PlayerAuraDuration" => AplValueType::PlayerAuraDuration,
             "TargetAuraExists" => AplValueType::TargetAuraExists,
@@ -119,13 +151,24 @@ impl FromJsonString for AplValueType {
             "SimTime" => AplValueType::SimTime,
             "SimTimePercent" => AplValueType::SimTimePercent,
             "SimDuration" => AplValueType::SimDuration,
+            "SpellCastTime" => AplValueType::SpellCastTime,
+            "SpellTravelTime" => AplValueType::SpellTravelTime,
+            "SpellTravelCastTime" => AplValueType::SpellTravelCastTime,
+            "SpellCanCast" => AplValueType::SpellCanCast,
+            "Add" => AplValueType::Add,
+            "Sub" => AplValueType::Sub,
+            "Mul" => AplValueType::Mul,
+            "Div" => AplValueType::Div,
+            "Min" => AplValueType::Min,
+            "Max" => AplValueType::Max,
+            "Abs" => AplValueType::Abs,
             _ => AplValueType::None,
         }
     }
 }
 
 // Helper function to convert APL action key string to Action enum
-fn apl_key_to_action(key: &str) -> Action {
+pub(crate) fn apl_key_to_action(key: &str) -> Action {
     match key {
         "ArcanePower" => Action::ArcanePower,
         "Berserking" => Action::Berserking,
@@ -146,6 +189,27 @@ fn apl_key_to_action(key: &str) -> Action {
     }
 }
 
+// Inverse of apl_key_to_action, for writing actions back into APL JSON
+pub(crate) fn action_to_apl_key(action: Action) -> &'static str {
+    match action {
+        Action::ArcanePower => "ArcanePower",
+        Action::Berserking => "Berserking",
+        Action::Combustion => "Combustion",
+        Action::Toep => "EphemeralPower",
+        Action::Sapp => "EssenceOfSapphiron",
+        Action::Fireball => "Fireball",
+        Action::FireBlast => "FireBlast",
+        Action::Frostbolt => "Frostbolt",
+        Action::Mqg => "MindQuickening",
+        Action::PowerInfusion => "PowerInfusion",
+        Action::PresenceOfMind => "PresenceOfMind",
+        Action::Pyroblast => "Pyroblast",
+        Action::Scorch => "Scorch",
+        Action::Zhc => "UnstablePower",
+        Action::Gcd => "Wait",
+    }
+}
+
 // Helper function to safely get f64 from JSON value
 fn get_f64_from_value(value: &Value, default: f64) -> f64 {
     match value {
@@ -199,6 +263,13 @@ impl From<&Value> for AplValue {
             value.vint = get_i32_from_value(vint, 0);
         }
         
+        // Parse operands of arithmetic values (recursive)
+        if let Some(values_array) = json.get("values").and_then(|v| v.as_array()) {
+            value.values = values_array.iter()
+                .map(AplValue::from)
+                .collect();
+        }
+        
         value
     }
 }
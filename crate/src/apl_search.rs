@@ -0,0 +1,179 @@
+//! apl_search.rs — local search over APL structure
+//!
+//! Edits a player's APL JSON one step at a time: drop a priority item, move an item
+//! one place up, or give an item another action the APL already uses (spells swap
+//! with spells, cooldowns with cooldowns). Each round draws fresh seeds and plays
+//! every candidate edit on the same fights as the current APL, so a candidate is
+//! judged by its paired per-iteration gain. Reorders and action swaps are kept only
+//! when the gain is significant; drops are kept unless they are significantly worse,
+//! against both the current and the input APL, which is what prunes dead items.
+//! Drops that each cost a little can still add up, so if the result validates
+//! significantly worse than the input, the input is returned unchanged.
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use crate::apl::{action_to_apl_key, extract_default_action, extract_items};
+use crate::constants::Action;
+use crate::orchestration::{iteration_seed, SimParams};
+use crate::stats::{Estimate, RunningStats};
+use crate::tuning::target_samples;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchSpec {
+    /// Players whose APLs are edited; empty means every player with an APL
+    pub players: Vec<usize>,
+    /// Iterations per candidate evaluation
+    pub iterations: i32,
+    /// Passes over all candidate edits; the search stops early once a pass keeps none
+    pub rounds: usize,
+    /// Standard errors a paired gain must clear to count as significant
+    pub z: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AplEdit {
+    Drop { player: usize, item: usize },
+    MoveUp { player: usize, item: usize },
+    SetAction { player: usize, item: usize, action: Action },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// The searched APLs, in the UI's JSON layout
+    pub apls: Vec<Option<Value>>,
+    /// Kept edits in the order they were applied
+    pub edits: Vec<AplEdit>,
+    pub items_before: Vec<usize>,
+    pub items_after: Vec<usize>,
+    /// Mean target DPS before and after, on validation seeds
+    pub baseline: f64,
+    pub searched: f64,
+    /// Paired per-iteration DPS gain on the validation seeds
+    pub improvement: Estimate,
+    pub evaluations: usize,
+    /// The searched APLs validated significantly worse, so the input was returned;
+    /// `edits`, `searched` and `improvement` still describe the rejected search
+    pub reverted: bool,
+}
+
+fn item_count(apl: &Option<Value>) -> usize {
+    apl.as_ref().map_or(0, |v| extract_items(v).len())
+}
+
+/// Every single edit of `player`'s APL
+fn candidate_edits(apl: &Value, player: usize) -> Vec<AplEdit> {
+    let items = extract_items(apl);
+    let mut pool: Vec<Action> = items.iter().map(|i| i.action).collect();
+    pool.push(extract_default_action(apl));
+    pool.sort_by_key(|a| action_to_apl_key(*a));
+    pool.dedup();
+
+    let mut edits = Vec::new();
+    for (item, it) in items.iter().enumerate() {
+        edits.push(AplEdit::Drop { player, item });
+        if item > 0 { edits.push(AplEdit::MoveUp { player, item }); }
+        for &action in &pool {
+            if action != it.action && action != Action::Gcd && action.triggers_gcd() == it.action.triggers_gcd() {
+                edits.push(AplEdit::SetAction { player, item, action });
+            }
+        }
+    }
+    edits
+}
+
+/// `apls` with `edit` applied, or None when it no longer fits
+fn apply_edit(apls: &[Option<Value>], edit: AplEdit) -> Option<Vec<Option<Value>>> {
+    let mut out = apls.to_vec();
+    let player = match edit {
+        AplEdit::Drop { player, .. } | AplEdit::MoveUp { player, .. } | AplEdit::SetAction { player, .. } => player,
+    };
+    let items = out.get_mut(player)?.as_mut()?.get_mut("items")?.as_array_mut()?;
+    match edit {
+        AplEdit::Drop { item, .. } if item < items.len() => { items.remove(item); }
+        AplEdit::MoveUp { item, .. } if item > 0 && item < items.len() => items.swap(item - 1, item),
+        AplEdit::SetAction { item, action, .. } if item < items.len() => {
+            // only the key: the action object may carry other fields the UI keeps
+            let slot = items[item].get_mut("action")?.as_object_mut()?;
+            slot.insert("key".to_string(), serde_json::json!(action_to_apl_key(action)));
+        }
+        _ => return None,
+    }
+    Some(out)
+}
+
+fn paired_gain(after: &[f64], before: &[f64]) -> Estimate {
+    let mut gain = RunningStats::default();
+    for (a, b) in after.iter().zip(before.iter()) { gain.push(a - b); }
+    Estimate::from(&gain)
+}
+
+/// Search the structure of `apls` for a shorter or better priority list
+pub fn search_apl(params: &SimParams, apls: &[Option<Value>], spec: &SearchSpec, seed: u64) -> SearchResult {
+    let players: Vec<usize> = if spec.players.is_empty() {
+        (0..apls.len()).filter(|&p| apls[p].is_some()).collect()
+    } else {
+        spec.players.iter().copied().filter(|&p| apls.get(p).is_some_and(Option::is_some)).collect()
+    };
+
+    let mut current = apls.to_vec();
+    let mut edits = Vec::new();
+    let mut evaluations = 0;
+
+    for round in 0..spec.rounds as u64 {
+        let round_seed = iteration_seed(seed, round);
+        let input = target_samples(params, apls, round_seed, spec.iterations);
+        let mut base = target_samples(params, &current, round_seed, spec.iterations);
+        evaluations += 2;
+        let mut kept = false;
+
+        for &player in &players {
+            // edits shift item indices, so regenerate after each kept one
+            let mut i = 0;
+            while let Some(apl) = current[player].as_ref() {
+                let Some(&edit) = candidate_edits(apl, player).get(i) else { break };
+                i += 1;
+                let Some(candidate) = apply_edit(&current, edit) else { continue };
+                let samples = target_samples(params, &candidate, round_seed, spec.iterations);
+                evaluations += 1;
+
+                let gain = paired_gain(&samples, &base);
+                let keep = match edit {
+                    AplEdit::Drop { .. } => {
+                        let vs_input = paired_gain(&samples, &input);
+                        gain.mean + spec.z * gain.std_error >= 0.0 && vs_input.mean + spec.z * vs_input.std_error >= 0.0
+                    }
+                    _ => gain.mean > spec.z * gain.std_error && gain.mean > 0.0,
+                };
+                if keep {
+                    current = candidate;
+                    base = samples;
+                    edits.push(edit);
+                    kept = true;
+                    i = 0;
+                }
+            }
+        }
+        if !kept { break; }
+    }
+
+    // fresh seeds so the kept edits are not judged on the fights that picked them
+    let validation_seed = iteration_seed(seed, u64::MAX);
+    let before = target_samples(params, apls, validation_seed, spec.iterations);
+    let after = target_samples(params, &current, validation_seed, spec.iterations);
+    let mean = |d: &[f64]| d.iter().sum::<f64>() / d.len().max(1) as f64;
+    let improvement = paired_gain(&after, &before);
+    let reverted = improvement.mean + spec.z * improvement.std_error < 0.0;
+    if reverted { current = apls.to_vec(); }
+
+    SearchResult {
+        items_before: apls.iter().map(item_count).collect(),
+        items_after: current.iter().map(item_count).collect(),
+        baseline: mean(&before),
+        searched: mean(&after),
+        improvement,
+        apls: current,
+        edits,
+        evaluations,
+        reverted,
+    }
+}
@@ -0,0 +1,100 @@
+//! branch.rs — fork a fight at a decision and compare continuations
+//!
+//! A `Simulation` is played with the normal decider up to a decision, then forked
+//! once per candidate action. Every fork plays the same `rollouts` continuations:
+//! rollout `r` of each candidate reseeds with the same seed, so candidate
+//! differences are paired and far less noisy than their means suggest.
+
+use serde::{Serialize, Deserialize};
+use crate::constants::Action;
+use crate::decisions::{legal_actions, Decider};
+use crate::orchestration::{iteration_seed, SimParams, Simulation};
+use crate::stats::{Estimate, RunningStats};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BranchOutcome {
+    pub action: Action,
+    /// Damage from the fork point to the end of the fight, ignite included
+    pub remaining_damage: Estimate,
+    /// Paired damage lost against the best candidate (0 for the best)
+    pub loss: Estimate,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BranchComparison {
+    pub time: f64,
+    pub lane: usize,
+    /// Number of decisions taken before the fork
+    pub decision: usize,
+    pub rollouts: usize,
+    /// What the decider itself would have done here
+    pub chosen: Option<Action>,
+    pub best: Action,
+    /// Best candidate first
+    pub outcomes: Vec<BranchOutcome>,
+}
+
+/// Fork `sim` once per candidate: `lane` starts the candidate (reaction rolled from
+/// `delay_sigma`), then a copy of `decider` plays on. Returns None without candidates.
+pub fn compare_actions<D: Decider + Clone>(sim: &Simulation, decider: &D, lane: usize, candidates: &[Action],
+                                           delay_sigma: f64, rollouts: usize, seed: u64) -> Option<BranchComparison> {
+    if candidates.is_empty() { return None; }
+    let start = sim.damage();
+
+    // remaining damage per candidate, per rollout
+    let damage: Vec<Vec<f64>> = candidates.iter().map(|&action| {
+        (0..rollouts as u64).map(|r| {
+            let mut fork = sim.clone().without_metrics();
+            fork.reseed(iteration_seed(seed, r));
+            fork.step_action(lane, action, delay_sigma);
+            fork.run(&mut decider.clone());
+            fork.damage() - start
+        }).collect()
+    }).collect();
+
+    let mean = |d: &[f64]| d.iter().sum::<f64>() / d.len().max(1) as f64;
+    let best = (0..candidates.len()).max_by(|&a, &b| mean(&damage[a]).total_cmp(&mean(&damage[b])))?;
+
+    let mut outcomes: Vec<BranchOutcome> = candidates.iter().zip(damage.iter()).map(|(&action, d)| {
+        let mut remaining = RunningStats::default();
+        let mut loss = RunningStats::default();
+        for (x, b) in d.iter().zip(damage[best].iter()) {
+            remaining.push(*x);
+            loss.push(b - x);
+        }
+        BranchOutcome { action, remaining_damage: Estimate::from(&remaining), loss: Estimate::from(&loss) }
+    }).collect();
+    outcomes.sort_by(|a, b| a.loss.mean.total_cmp(&b.loss.mean));
+
+    Some(BranchComparison {
+        time: sim.time(),
+        lane,
+        decision: sim.decisions(),
+        rollouts,
+        chosen: decider.clone().next_action(&sim.st).filter(|&(l, _, _)| l == lane).map(|(_, a, _)| a),
+        best: candidates[best],
+        outcomes,
+    })
+}
+
+/// Play iteration `idx` with `decider` until the first decision at or after `t`, then
+/// compare every legal action of the lane deciding there.
+pub fn branch_at_time<D: Decider + Clone>(params: &SimParams, mut decider: D, seed: u64, idx: u64, t: f64, rollouts: usize) -> Option<BranchComparison> {
+    let mut sim = Simulation::new(params, seed, idx, false).without_metrics();
+    sim.run_to_time(&mut decider, t);
+    branch_here(params, &sim, &decider, seed, rollouts)
+}
+
+/// Like `branch_at_time`, forking after the first `n` decisions
+pub fn branch_at_decision<D: Decider + Clone>(params: &SimParams, mut decider: D, seed: u64, idx: u64, n: usize, rollouts: usize) -> Option<BranchComparison> {
+    let mut sim = Simulation::new(params, seed, idx, false).without_metrics();
+    sim.run_to_decision(&mut decider, n);
+    branch_here(params, &sim, &decider, seed, rollouts)
+}
+
+fn branch_here<D: Decider + Clone>(params: &SimParams, sim: &Simulation, decider: &D, seed: u64, rollouts: usize) -> Option<BranchComparison> {
+    if !sim.in_progress() { return None; }
+    let lane = sim.next_lane()?;
+    let candidates = legal_actions(&sim.st, lane);
+    compare_actions(sim, decider, lane, &candidates, params.timing.recast_delay, rollouts, seed)
+}
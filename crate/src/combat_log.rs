@@ -0,0 +1,152 @@
+//! combat_log.rs — export a logged iteration as `WoWCombatLog.txt` lines
+//!
+//! Uses the Classic combat log layout without advanced logging, so the same
+//! parsers that read real raid logs can read simulated fights. The fight starts
+//! at `LOG_START` on 1/1; mages are `Player-0-<lane>` units, the boss is a
+//! creature unit.
+
+use std::fmt::Write;
+use crate::constants::{Action, BossType, DamageSource};
+use crate::events::{CombatEvent, EventKind};
+use crate::orchestration::SpellResult;
+
+/// Wall clock of the pull, seconds after midnight (20:00:00)
+const LOG_START: f64 = 20.0 * 3600.0;
+
+const HEADER: &str = "COMBAT_LOG_VERSION,9,ADVANCED_LOG_ENABLED,0,BUILD_VERSION,1.15.2,PROJECT_ID,2";
+
+// unit flags: raid member / friendly / player-controlled player, hostile outsider NPC
+const PLAYER_FLAGS: &str = "0x514";
+const BOSS_FLAGS: &str = "0xa48";
+const NO_UNIT: &str = "0000000000000000,nil,0x80000000,0x80000000";
+
+const SCHOOL_PHYSICAL: u32 = 0x1;
+const SCHOOL_HOLY: u32 = 0x2;
+const SCHOOL_FIRE: u32 = 0x4;
+const SCHOOL_FROST: u32 = 0x10;
+const SCHOOL_ARCANE: u32 = 0x40;
+
+/// Spell id, name and school of a cast (max rank)
+fn action_spell(action: Action) -> Option<(u32, &'static str, u32)> {
+    Some(match action {
+        Action::Scorch => (10207, "Scorch", SCHOOL_FIRE),
+        Action::Pyroblast => (18809, "Pyroblast", SCHOOL_FIRE),
+        Action::Fireball => (25306, "Fireball", SCHOOL_FIRE),
+        Action::FireBlast => (10199, "Fire Blast", SCHOOL_FIRE),
+        Action::Frostbolt => (25304, "Frostbolt", SCHOOL_FROST),
+        Action::Combustion => (11129, "Combustion", SCHOOL_FIRE),
+        Action::Sapp => (28779, "Essence of Sapphiron", SCHOOL_ARCANE),
+        Action::Toep => (23271, "Ephemeral Power", SCHOOL_PHYSICAL),
+        Action::Zhc => (24658, "Unstable Power", SCHOOL_PHYSICAL),
+        Action::Mqg => (23723, "Mind Quickening", SCHOOL_PHYSICAL),
+        Action::PowerInfusion => (10060, "Power Infusion", SCHOOL_HOLY),
+        Action::Berserking => (20554, "Berserking", SCHOOL_PHYSICAL),
+        Action::ArcanePower => (12042, "Arcane Power", SCHOOL_ARCANE),
+        Action::PresenceOfMind => (12043, "Presence of Mind", SCHOOL_ARCANE),
+        Action::Gcd => return None,
+    })
+}
+
+const LOGGED_ACTIONS: [Action; 14] = [
+    Action::Scorch, Action::Pyroblast, Action::Fireball, Action::FireBlast, Action::Frostbolt,
+    Action::Combustion, Action::Sapp, Action::Toep, Action::Zhc, Action::Mqg,
+    Action::PowerInfusion, Action::Berserking, Action::ArcanePower, Action::PresenceOfMind,
+];
+
+/// Action behind a logged spell, by id or, for other ranks, by name
+pub(crate) fn action_from_spell(id: u32, name: &str) -> Option<Action> {
+    LOGGED_ACTIONS.iter().copied().find(|&a| action_spell(a).is_some_and(|(aid, aname, _)| aid == id || aname.eq_ignore_ascii_case(name)))
+}
+
+fn source_spell(source: DamageSource) -> (u32, &'static str, u32) {
+    match source {
+        DamageSource::Scorch => (10207, "Scorch", SCHOOL_FIRE),
+        DamageSource::Pyroblast | DamageSource::PyroDot => (18809, "Pyroblast", SCHOOL_FIRE),
+        DamageSource::Fireball => (25306, "Fireball", SCHOOL_FIRE),
+        DamageSource::FireBlast => (10199, "Fire Blast", SCHOOL_FIRE),
+        DamageSource::Frostbolt => (25304, "Frostbolt", SCHOOL_FROST),
+        DamageSource::Ignite => (12654, "Ignite", SCHOOL_FIRE),
+    }
+}
+
+/// NPC id, name and encounter id of the boss unit
+fn boss_unit(boss: BossType) -> (u32, &'static str, u32) {
+    match boss {
+        BossType::Loatheb => (16011, "Loatheb", 1115),
+        BossType::Thaddius => (15928, "Thaddius", 1120),
+        BossType::None => (0, "Boss", 0),
+    }
+}
+
+fn timestamp(t: f64) -> String {
+    let ms = ((LOG_START + t.max(0.0)) * 1000.0).round() as u64;
+    let (s, ms) = (ms / 1000, ms % 1000);
+    format!("1/1 {:02}:{:02}:{:02}.{:03}", s / 3600 % 24, s / 60 % 60, s % 60, ms)
+}
+
+/// Render `events` as combat log text; `names` are the lane names, `duration` the fight length
+pub fn export_combat_log(events: &[CombatEvent], names: &[String], boss: BossType, duration: f64) -> String {
+    let (npc, boss_name, encounter) = boss_unit(boss);
+    let boss_unit = format!("Creature-0-0-0-0-{}-0000000000,\"{}\",{},0x0", npc, boss_name, BOSS_FLAGS);
+    let player_unit = |lane: usize| {
+        let name = names.get(lane).map(String::as_str).unwrap_or("Unknown");
+        format!("Player-0-{:08X},\"{}\",{},0x0", lane + 1, name, PLAYER_FLAGS)
+    };
+
+    // (time, record) pairs; cast starts carry their reaction delay so sort at the end.
+    // A cast start is dropped again when its lane's next CAST_SUCCESS lands at the
+    // same time: instants (and PoM casts) only get a CAST_SUCCESS.
+    let mut lines: Vec<Option<(f64, String)>> = Vec::with_capacity(events.len());
+    let mut pending_start: Vec<Option<(usize, f64)>> = vec![None; names.len()];
+    for e in events {
+        let Some(lane) = e.lane else { continue };
+        if e.kind == EventKind::CastSuccess {
+            let instant = pending_start.get_mut(lane).and_then(Option::take).filter(|&(_, start)| e.t - start < 1e-6);
+            if let Some((idx, _)) = instant { lines[idx] = None; }
+        }
+        let record = match e.kind {
+            EventKind::CastStart | EventKind::CastSuccess => {
+                let Some(action) = e.action else { continue };
+                let Some((id, name, school)) = action_spell(action) else { continue };
+                if e.kind == EventKind::CastStart {
+                    if lane >= pending_start.len() { pending_start.resize(lane + 1, None); }
+                    pending_start[lane] = Some((lines.len(), e.t));
+                    format!("SPELL_CAST_START,{},{},{},\"{}\",0x{:x}", player_unit(lane), NO_UNIT, id, name, school)
+                } else if action == Action::PowerInfusion {
+                    // cast by a priest outside the sim; show the buff landing instead
+                    format!("SPELL_AURA_APPLIED,{},{},{},\"{}\",0x{:x},BUFF", NO_UNIT, player_unit(lane), id, name, school)
+                } else {
+                    let target = if action.triggers_gcd() { boss_unit.as_str() } else { NO_UNIT };
+                    format!("SPELL_CAST_SUCCESS,{},{},{},\"{}\",0x{:x}", player_unit(lane), target, id, name, school)
+                }
+            }
+            EventKind::SpellImpact | EventKind::PeriodicDamage | EventKind::IgniteTick => {
+                let Some(source) = e.source else { continue };
+                let (id, name, school) = source_spell(source);
+                let prefix = format!("{},{},{},\"{}\",0x{:x}", player_unit(lane), boss_unit, id, name, school);
+                if e.result == SpellResult::Miss {
+                    format!("SPELL_MISSED,{},RESIST,nil", prefix)
+                } else {
+                    let event = if e.kind == EventKind::SpellImpact { "SPELL_DAMAGE" } else { "SPELL_PERIODIC_DAMAGE" };
+                    let amount = e.amount.round();
+                    let resisted = if e.partial > 0.0 { (e.amount / e.partial - e.amount).round() } else { 0.0 };
+                    let crit = if e.result == SpellResult::Crit { "1" } else { "nil" };
+                    format!("{},{},{},-1,{},{},0,0,{},nil,nil,nil", event, prefix, amount, school, resisted, crit)
+                }
+            }
+        };
+        lines.push(Some((e.t, record)));
+    }
+    let mut lines: Vec<(f64, String)> = lines.into_iter().flatten().collect();
+    lines.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let group = names.len();
+    let mut out = String::new();
+    let _ = writeln!(out, "{}  {}", timestamp(0.0), HEADER);
+    let _ = writeln!(out, "{}  ENCOUNTER_START,{},\"{}\",9,{}", timestamp(0.0), encounter, boss_name, group);
+    for (t, record) in &lines {
+        let _ = writeln!(out, "{}  {}", timestamp(*t), record);
+    }
+    let _ = writeln!(out, "{}  ENCOUNTER_END,{},\"{}\",9,{},1", timestamp(duration), encounter, boss_name, group);
+    out
+}
@@ -152,6 +152,43 @@ impl fmt::Display for Spell {
         }
     }
 }
+/// Where damage comes from: every `Spell` plus ignite ticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DamageSource { Scorch = 0, Pyroblast = 1, Fireball = 2, FireBlast = 3, Frostbolt = 4, PyroDot = 5, Ignite = 6 }
+
+pub const NUM_DAMAGE_SOURCES: usize = 7;
+pub const DAMAGE_SOURCES: [DamageSource; NUM_DAMAGE_SOURCES] = [
+    DamageSource::Scorch, DamageSource::Pyroblast, DamageSource::Fireball, DamageSource::FireBlast,
+    DamageSource::Frostbolt, DamageSource::PyroDot, DamageSource::Ignite,
+];
+
+impl From<Spell> for DamageSource {
+    fn from(spell: Spell) -> Self {
+        match spell {
+            Spell::Scorch => DamageSource::Scorch,
+            Spell::Pyroblast => DamageSource::Pyroblast,
+            Spell::Fireball => DamageSource::Fireball,
+            Spell::FireBlast => DamageSource::FireBlast,
+            Spell::Frostbolt => DamageSource::Frostbolt,
+            Spell::PyroDot => DamageSource::PyroDot,
+        }
+    }
+}
+
+impl fmt::Display for DamageSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DamageSource::Ignite => write!(f, "Ignite"),
+            DamageSource::Scorch => Spell::Scorch.fmt(f),
+            DamageSource::Pyroblast => Spell::Pyroblast.fmt(f),
+            DamageSource::Fireball => Spell::Fireball.fmt(f),
+            DamageSource::FireBlast => Spell::FireBlast.fmt(f),
+            DamageSource::Frostbolt => Spell::Frostbolt.fmt(f),
+            DamageSource::PyroDot => Spell::PyroDot.fmt(f),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Action {
     // Castable spells
@@ -286,6 +323,18 @@ impl Action {
         use Action::*;
         matches!(self, Scorch | Pyroblast | Fireball | FireBlast | Frostbolt)
     }
+
+    /// The spell a casting action fires, None for cooldowns and GCD waits
+    pub fn spell(self) -> Option<Spell> {
+        match self {
+            Action::Scorch => Some(Spell::Scorch),
+            Action::Pyroblast => Some(Spell::Pyroblast),
+            Action::Fireball => Some(Spell::Fireball),
+            Action::FireBlast => Some(Spell::FireBlast),
+            Action::Frostbolt => Some(Spell::Frostbolt),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
@@ -376,6 +425,9 @@ pub const T3_6P_TIMER: f64 = 30.0;
 
 pub const T2_8P_CHANCE: f64 = 0.1;
 
+/// Master seed used when the config doesn't carry one
+pub const DEFAULT_RNG_SEED: u64 = 42;
+
 /// How many opening Scorches are required by number of mages (index by num_mages)
 pub const SCORCHES_BY_MAGES: [i32; 13] = [9000, 6, 3, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1];
 
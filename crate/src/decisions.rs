@@ -1,8 +1,9 @@
 //! decisions.rs — rotation logic
 use crate::constants as C;
 use crate::constants::{Action, Buff};
+use serde::{Serialize, Deserialize};
 use crate::state::State;
-use crate::apl::{AplItem, AplConditionType, AplConditionOp, AplCondition, AplValue, AplValueType};
+use crate::apl::{AplItem, AplConditionType, AplConditionOp, AplCondition, AplValue, AplValueType, apl_key_to_action};
 
 fn action_to_buff(action: Action) -> Option<Buff> {
     match action {
@@ -14,7 +15,7 @@ fn action_to_buff(action: Action) -> Option<Buff> {
     }
 }
 
-fn action_ready_for_action(st: &State, lane: usize, action: Action) -> bool {
+pub(crate) fn action_ready_for_action(st: &State, lane: usize, action: Action) -> bool {
     if action_to_buff(action).is_some() {
         if let Some(buff) = action_to_buff(action) {
             return st.lanes[lane].buff_cooldown[buff as usize] <= 0.0
@@ -41,16 +42,62 @@ fn action_ready_for_action(st: &State, lane: usize, action: Action) -> bool {
 }
 
 
+/// Actions `lane` could start right now: every spell it can cast plus every
+/// cooldown that is ready. Waiting on a GCD is not included.
+pub fn legal_actions(st: &State, lane: usize) -> Vec<Action> {
+    use Action as A;
+    [A::Scorch, A::Fireball, A::Pyroblast, A::FireBlast, A::Frostbolt,
+     A::Combustion, A::Sapp, A::Toep, A::Zhc, A::Mqg, A::PowerInfusion, A::Berserking, A::ArcanePower, A::PresenceOfMind]
+        .into_iter()
+        .filter(|&a| action_ready_for_action(st, lane, a))
+        .collect()
+}
+
+/// What made a decider pick its last action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DecisionSource {
+    /// Step of the opener sequence
+    Opener(usize),
+    /// Priority list item
+    Rule(usize),
+    /// Nothing matched; the default action
+    Default,
+}
+
 pub trait Decider {
     fn next_action(&mut self, st: &State) -> Option<(usize, Action, f64)>;
+
+    /// Why `lane`'s last action was picked, for deciders that can tell
+    fn last_rule(&self, _lane: usize) -> Option<DecisionSource> { None }
+
+    /// Exact delay before the action just chosen starts. `None` (the default) rolls a
+    /// reaction delay from the sigma returned by `next_action`.
+    fn fixed_delay(&mut self, _st: &State, _lane: usize) -> Option<f64> { None }
+
+    /// Reads states with reaction time, so `State` has to date their changes
+    fn watches(&self) -> bool { false }
 }
 
 pub trait MageDecider {
     /// Decide next action for *this lane only*.
     /// Return (action, reaction_sigma) or None to skip.
     fn decide(&mut self, state: &State, lane: usize) -> Option<(Action, f64)>;
+
+    /// Copy of this decider, progress included, for forked simulations
+    fn box_clone(&self) -> Box<dyn MageDecider>;
+
+    /// Why the last action was picked, if the decider tracks it
+    fn last_rule(&self) -> Option<DecisionSource> { None }
+
+    /// See `Decider::watches`
+    fn watches(&self) -> bool { false }
 }
 
+impl Clone for Box<dyn MageDecider> {
+    fn clone(&self) -> Self { self.box_clone() }
+}
+
+#[derive(Clone)]
 pub struct ScriptedMage {
     stage: usize,            // per-lane progress
     initial_sequence: Vec<Action>, // opener shared by all lanes
@@ -88,8 +135,11 @@ impl MageDecider for ScriptedMage {
         self.stage = self.initial_sequence.len();        
         Some((self.default_action, self.recast_delay))
     }
+
+    fn box_clone(&self) -> Box<dyn MageDecider> { Box::new(self.clone()) }
 }
 
+#[derive(Clone)]
 pub struct TeamDecider {
     mages: Vec<Box<dyn MageDecider>>,
 }
@@ -106,6 +156,14 @@ impl Decider for TeamDecider {
         let (act, sig) = self.mages[lane].decide(st, lane)?;
         Some((lane, act, sig))
     }
+
+    fn last_rule(&self, lane: usize) -> Option<DecisionSource> {
+        self.mages.get(lane)?.last_rule()
+    }
+
+    fn watches(&self) -> bool {
+        self.mages.iter().any(|m| m.watches())
+    }
 }
 
 // Updated enum with Auto context
@@ -116,6 +174,60 @@ enum ValueContext {
     Auto,  // For constants when both constants are being compared
 }
 
+fn infer_value_context(value: &AplValue) -> ValueContext {
+    match value.value_type {
+        // Arithmetic takes the context of its first non-constant operand
+        AplValueType::Add | AplValueType::Sub | AplValueType::Mul | AplValueType::Div |
+        AplValueType::Min | AplValueType::Max | AplValueType::Abs => {
+            value.values.iter()
+                .map(infer_value_context)
+                .find(|c| !matches!(c, ValueContext::Auto))
+                .unwrap_or(ValueContext::Auto)
+        }
+        value_type => infer_value_context_from_type(value_type),
+    }
+}
+
+fn infer_value_context_from_type(value_type: AplValueType) -> ValueContext {
+    
+    match value_type {
+        AplValueType::SimTime | AplValueType::SimTimePercent | AplValueType::SimDuration |
+        AplValueType::PlayerCooldownDuration | AplValueType::PlayerAuraDuration | AplValueType::TargetAuraDuration |
+        AplValueType::SpellCastTime | AplValueType::SpellTravelTime | AplValueType::SpellTravelCastTime => {
+            ValueContext::Float
+        }
+        AplValueType::PlayerCooldownExists | AplValueType::PlayerAuraExists | AplValueType::TargetAuraExists |
+        AplValueType::TargetAuraStacks | AplValueType::SpellCanCast |
+        AplValueType::PlayerCooldownReact | AplValueType::PlayerAuraReact | AplValueType::TargetAuraReact => {
+            ValueContext::Int
+        }
+        AplValueType::Const => ValueContext::Auto, // Will be determined by context
+        _ => ValueContext::Float,
+    }
+}
+
+// Context of a comparison, from its non-constant side
+fn comparison_context(left_val: &AplValue, right_val: &AplValue) -> ValueContext {
+    match (infer_value_context(left_val), infer_value_context(right_val)) {
+        (ValueContext::Auto, other) | (other, ValueContext::Auto) => other,
+        (left, _) => {
+            // Both non-constant, use left side to determine context
+            left
+        }
+    }
+}
+
+/// Whether the constants among `condition.values` are read through their `vint` slot
+pub(crate) fn condition_reads_int(condition: &AplCondition) -> bool {
+    let context = match condition.condition_type {
+        AplConditionType::Cmp if condition.values.len() >= 2 => comparison_context(&condition.values[0], &condition.values[1]),
+        AplConditionType::True | AplConditionType::False if !condition.values.is_empty() => infer_value_context(&condition.values[0]),
+        _ => ValueContext::Auto,
+    };
+    matches!(context, ValueContext::Int)
+}
+
+#[derive(Clone)]
 pub struct AdaptiveMage {
     stage: usize,            // per-lane progress in opener
     initial_sequence: Vec<Action>, // opener shared by all lanes
@@ -123,6 +235,8 @@ pub struct AdaptiveMage {
     default_action: Action,
     recast_delay: f64,
     reaction_time: f64,
+    last_rule: Option<DecisionSource>,
+    watches: bool,           // some rule reads a React value
 }
 
 impl AdaptiveMage {
@@ -133,6 +247,7 @@ impl AdaptiveMage {
         recast_delay: f64,
         reaction_time: f64,
     ) -> Self {
+        let watches = items.iter().any(|item| item.condition.reads_reaction());
         Self {
             stage: 0,
             initial_sequence,
@@ -140,15 +255,17 @@ impl AdaptiveMage {
             default_action,
             recast_delay,
             reaction_time,
+            last_rule: None,
+            watches,
         }
     }
 
-    fn conditional_action(&self, items: &[AplItem], st: &State, lane: usize) -> Option<Action> {
+    fn conditional_action(&self, items: &[AplItem], st: &State, lane: usize) -> Option<(usize, Action)> {
         // Iterate through items in priority order
-        for item in items {
+        for (idx, item) in items.iter().enumerate() {
             if self.evaluate_condition(&item.condition, st, lane) {
                 if action_ready_for_action(st, lane, item.action) {
-                    return Some(item.action);
+                    return Some((idx, item.action));
                 }
             }
         }
@@ -161,7 +278,7 @@ impl AdaptiveMage {
             AplConditionType::None => true,
             AplConditionType::True => {
                 if condition.values.len() >= 1 {
-                    let context = self.infer_value_context(&condition.values[0]);
+                    let context = infer_value_context(&condition.values[0]);
                     self.evaluate_value(&condition.values[0], st, lane, context) != 0.0
                 } else {
                     false
@@ -169,7 +286,7 @@ impl AdaptiveMage {
             }
             AplConditionType::False => {
                 if condition.values.len() >= 1 {
-                    let context = self.infer_value_context(&condition.values[0]);
+                    let context = infer_value_context(&condition.values[0]);
                     self.evaluate_value(&condition.values[0], st, lane, context) == 0.0
                 } else {
                     false
@@ -200,16 +317,8 @@ impl AdaptiveMage {
 
     fn compare_two_values(&self, left_val: &AplValue, right_val: &AplValue, op: &AplConditionOp, st: &State, lane: usize) -> bool {
 
-        // Determine comparison context based on non-constant types
-        let context = match (left_val.value_type, right_val.value_type) {
-            (AplValueType::Const, other) | (other, AplValueType::Const) => {
-                self.infer_value_context_from_type(other)
-            }
-            (left_type, _) => {
-                // Both non-constant, use left side to determine context
-                self.infer_value_context_from_type(left_type)
-            }
-        };
+        // Determine comparison context based on non-constant sides
+        let context = comparison_context(left_val, right_val);
         // special case: Ignite or scorch debuff
         if left_val.value_type == AplValueType::TargetAuraDuration || right_val.value_type == AplValueType::TargetAuraDuration {
             return self.compare_debuff_duration_with_reaction_time(left_val, right_val, op, st, lane, context);
@@ -221,26 +330,6 @@ impl AdaptiveMage {
         self.compare_values(left, right, op)
     }
 
-    fn infer_value_context(&self, value: &AplValue) -> ValueContext {
-        self.infer_value_context_from_type(value.value_type)
-    }
-
-    fn infer_value_context_from_type(&self, value_type: AplValueType) -> ValueContext {
-        
-        match value_type {
-            AplValueType::SimTime | AplValueType::SimTimePercent | AplValueType::SimDuration |
-            AplValueType::PlayerCooldownDuration | AplValueType::PlayerAuraDuration | AplValueType::TargetAuraDuration => {
-                ValueContext::Float
-            }
-            AplValueType::PlayerCooldownExists | AplValueType::PlayerAuraExists | AplValueType::TargetAuraExists |
-            AplValueType::TargetAuraStacks => {
-                ValueContext::Int
-            }
-            AplValueType::Const => ValueContext::Auto, // Will be determined by context
-            _ => ValueContext::Float,
-        }
-    }
-
     fn get_const_value(&self, value: &AplValue, context: ValueContext) -> f64 {
         // Step 2: Check both int and float, use non-zero value
         let has_float = value.vfloat != 0.0;
@@ -268,16 +357,6 @@ impl AdaptiveMage {
         }
     }
 
-    fn js_constant_to_buff(&self, constant: i32) -> Option<Buff> {
-        match constant {
-            28779 => Some(Buff::Sapp),    // ESSENCE_OF_SAPPHIRON
-            23723 => Some(Buff::Mqg),     // MIND_QUICKENING  
-            23271 => Some(Buff::Toep),    // EPHEMERAL_POWER
-            24658 => Some(Buff::Zhc),     // UNSTABLE_POWER
-            _ => None,
-        }
-    }
-
     fn evaluate_value(&self, value: &AplValue, st: &State, lane: usize, context: ValueContext) -> f64 {
 
         match value.value_type {
@@ -288,81 +367,28 @@ impl AdaptiveMage {
             AplValueType::SimTimePercent => (st.global.running_time / st.global.duration) * 100.0,
             AplValueType::SimDuration => st.global.duration - st.global.running_time,
             
+            // cooldown, aura and debuff ids map to timers in one place, the `State::watched_*` accessors
             AplValueType::PlayerCooldownExists => {
-                match value.vint {
-                    29977 => if st.lanes[lane].comb_cooldown > 0.0 { 1.0 } else { 0.0 }, // COMBUSTION
-                    10199 => if st.lanes[lane].fb_cooldown > 0.0 { 1.0 } else { 0.0 },   // FIRE_BLAST
-                    10060 => if st.lanes[lane].pi_cooldown.iter().cloned().reduce(f64::min).unwrap() > 0.0 { 1.0 } else { 0.0 },   // PI
-                    12042 => if st.lanes[lane].ap_cooldown > 0.0 { 1.0 } else { 0.0 },
-                    12043 => if st.lanes[lane].pom_cooldown > 0.0 { 1.0 } else { 0.0 },
-                    20554 => if st.lanes[lane].berserk_cooldown > 0.0 { 1.0 } else { 0.0 },
-                    _ => {
-                        if let Some(buff) = self.js_constant_to_buff(value.vint) {
-                            if st.lanes[lane].buff_cooldown[buff as usize] > 0.0 { 1.0 } else { 0.0 }
-                        } else {
-                            0.0
-                        }
-                    }
-                }
+                if st.watched_cooldown(lane, value.vint) > 0.0 { 1.0 } else { 0.0 }
             }
-            
-            AplValueType::PlayerCooldownDuration => {
-                match value.vint {
-                    29977 => st.lanes[lane].comb_cooldown.max(0.0), // COMBUSTION
-                    10199 => st.lanes[lane].fb_cooldown.max(0.0),   // FIRE_BLAST
-                    10060 => st.lanes[lane].pi_cooldown.iter().cloned().reduce(f64::min).unwrap().max(0.0),
-                    12042 => st.lanes[lane].ap_cooldown.max(0.0),
-                    12043 => st.lanes[lane].pom_cooldown.max(0.0),
-                    20554 => st.lanes[lane].berserk_cooldown.max(0.0),
-                    _ => {
-                        if let Some(buff) = self.js_constant_to_buff(value.vint) {
-                            st.lanes[lane].buff_cooldown[buff as usize].max(0.0)
-                        } else {
-                            0.0
-                        }
-                    }
-                }
-            }
-            
+
+            AplValueType::PlayerCooldownDuration => st.watched_cooldown(lane, value.vint).max(0.0),
+
             AplValueType::PlayerAuraExists => {
                 match value.vint {
-                    29977 => st.lanes[lane].comb_left as f64, // COMBUSTION - use comb_left
-                    10060 => if st.lanes[lane].pi_timer.iter().cloned().reduce(f64::max).unwrap() > 0.0 { 1.0 } else { 0.0 },
-                    12042 => if st.lanes[lane].ap_timer > 0.0 {1.0} else { 0.0 },
-                    12043 => if st.lanes[lane].pom_active {1.0} else { 0.0 },
-                    20554 => if st.lanes[lane].berserk_timer > 0.0 {1.0} else { 0.0 },
-                    _ => {
-                        if let Some(buff) = self.js_constant_to_buff(value.vint) {
-                            if st.lanes[lane].buff_timer[buff as usize] > 0.0 { 1.0 } else { 0.0 }
-                        } else {
-                            0.0
-                        }
-                    }
+                    29977 => st.lanes[lane].comb_left as f64, // COMBUSTION - charges left
+                    id => if st.watched_aura(lane, id) > 0.0 { 1.0 } else { 0.0 },
                 }
             }
-            
+
+            // auras that end on use (Combustion, Presence of Mind) have no duration
             AplValueType::PlayerAuraDuration => {
-                match value.vint {
-                    29977 => 0.0, // COMBUSTION - no duration for combustion aura
-                    10060 => st.lanes[lane].pi_timer.iter().cloned().reduce(f64::max).unwrap().max(0.0),
-                    12042 => st.lanes[lane].ap_timer.max(0.0),
-                    20554 => st.lanes[lane].berserk_timer.max(0.0),
-                    _ => {
-                        if let Some(buff) = self.js_constant_to_buff(value.vint) {
-                            st.lanes[lane].buff_timer[buff as usize].max(0.0)
-                        } else {
-                            0.0
-                        }
-                    }
-                }
+                let remaining = st.watched_aura(lane, value.vint);
+                if remaining.is_finite() { remaining.max(0.0) } else { 0.0 }
             }
-            
+
             AplValueType::TargetAuraExists => {
-                match value.vint {
-                    22959 => if st.boss.scorch_timer > 0.0 { 1.0 } else { 0.0 }, // FIRE_VULNERABILITY
-                    12654 => if st.boss.ignite_timer > 0.0 { 1.0 } else { 0.0 }, // Ignite
-                    _ => 0.0,
-                }
+                if st.watched_debuff(value.vint) > 0.0 { 1.0 } else { 0.0 }
             }
             
             AplValueType::TargetAuraStacks => {
@@ -373,18 +399,60 @@ impl AdaptiveMage {
                 }
             }
             
-            AplValueType::TargetAuraDuration => {
-                match value.vint {
-                    22959 => st.boss.scorch_timer.max(0.0), // FIRE_VULNERABILITY
-                    12654 => st.boss.ignite_timer.max(0.0),     // Ignite
-                    _ => 0.0,
-                }
+            AplValueType::TargetAuraDuration => st.watched_debuff(value.vint).max(0.0),
+            
+            // Exists, as this lane saw it one reaction time ago
+            AplValueType::PlayerCooldownReact | AplValueType::PlayerAuraReact | AplValueType::TargetAuraReact => {
+                let seen = match value.value_type {
+                    AplValueType::PlayerCooldownReact => st.cooldown_seen(lane, value.vint, self.reaction_time),
+                    AplValueType::PlayerAuraReact => st.aura_seen(lane, value.vint, self.reaction_time),
+                    _ => st.debuff_seen(value.vint, self.reaction_time),
+                };
+                if seen { 1.0 } else { 0.0 }
+            }
+            
+            // vstr holds the spell's APL key; cooldowns and unknown keys take no time
+            AplValueType::SpellCastTime => {
+                apl_key_to_action(&value.vstr).spell().map_or(0.0, |s| st.spell_cast_time(lane, s))
+            }
+            AplValueType::SpellTravelTime => {
+                apl_key_to_action(&value.vstr).spell().map_or(0.0, |s| st.spell_travel_time(lane, s))
+            }
+            AplValueType::SpellTravelCastTime => {
+                apl_key_to_action(&value.vstr).spell()
+                    .map_or(0.0, |s| st.spell_cast_time(lane, s) + st.spell_travel_time(lane, s))
+            }
+            AplValueType::SpellCanCast => {
+                let action = apl_key_to_action(&value.vstr);
+                if action != Action::Gcd && action_ready_for_action(st, lane, action) { 1.0 } else { 0.0 }
+            }
+            
+            AplValueType::Add | AplValueType::Sub | AplValueType::Mul | AplValueType::Div |
+            AplValueType::Min | AplValueType::Max | AplValueType::Abs => {
+                self.evaluate_arithmetic(value, st, lane, context)
             }
             
             _ => 0.0,
         }
     }
 
+    // Operands fold left to right; a missing operand is 0 and dividing by 0 gives 0
+    fn evaluate_arithmetic(&self, value: &AplValue, st: &State, lane: usize, context: ValueContext) -> f64 {
+        let mut operands = value.values.iter().map(|v| self.evaluate_value(v, st, lane, context));
+
+        let result = match value.value_type {
+            AplValueType::Add => operands.reduce(|a, b| a + b),
+            AplValueType::Sub => operands.reduce(|a, b| a - b),
+            AplValueType::Mul => operands.reduce(|a, b| a * b),
+            AplValueType::Div => operands.reduce(|a, b| if b != 0.0 { a / b } else { 0.0 }),
+            AplValueType::Min => operands.reduce(f64::min),
+            AplValueType::Max => operands.reduce(f64::max),
+            AplValueType::Abs => operands.next().map(f64::abs),
+            _ => None,
+        };
+        result.unwrap_or(0.0)
+    }
+
     fn compare_values(&self, left: f64, right: f64, op: &AplConditionOp) -> bool {
         use crate::apl::AplConditionOp;
         
@@ -487,6 +555,7 @@ impl AdaptiveMage {
 
 }
 
+#[derive(Clone)]
 pub struct AdaptiveTeamDecider {
     mages: Vec<Box<dyn MageDecider>>,
 }
@@ -503,6 +572,14 @@ impl Decider for AdaptiveTeamDecider {
         let (act, sig) = self.mages[lane].decide(st, lane)?;
         Some((lane, act, sig))
     }
+
+    fn last_rule(&self, lane: usize) -> Option<DecisionSource> {
+        self.mages.get(lane)?.last_rule()
+    }
+
+    fn watches(&self) -> bool {
+        self.mages.iter().any(|m| m.watches())
+    }
 }
 
 impl MageDecider for AdaptiveMage {
@@ -515,6 +592,7 @@ impl MageDecider for AdaptiveMage {
             // For buff actions, check if they're ready; for non-buff actions, always proceed
             if action_ready_for_action(st, lane, action) {
                 self.stage = s + 1;
+                self.last_rule = Some(DecisionSource::Opener(s));
                 return Some((action, self.recast_delay));
             }
             s += 1;
@@ -522,12 +600,20 @@ impl MageDecider for AdaptiveMage {
         self.stage = self.initial_sequence.len();
         
         // Opener is complete, now use the adaptive priority list
-        if let Some(action) = self.conditional_action(&self.items, st, lane) {
+        if let Some((idx, action)) = self.conditional_action(&self.items, st, lane) {
             // Check if this action is ready (for buff actions)
+            self.last_rule = Some(DecisionSource::Rule(idx));
             return Some((action, self.recast_delay));
         }
         
         // Fall back to default action
+        self.last_rule = Some(DecisionSource::Default);
         Some((self.default_action, self.recast_delay))
     }
+
+    fn box_clone(&self) -> Box<dyn MageDecider> { Box::new(self.clone()) }
+
+    fn last_rule(&self) -> Option<DecisionSource> { self.last_rule }
+
+    fn watches(&self) -> bool { self.watches }
 }
\ No newline at end of file
@@ -0,0 +1,197 @@
+//! events.rs — typed combat log of a single iteration
+//!
+//! `State` pushes one `CombatEvent` per cast, hit, miss and tick while
+//! `log_enabled` is set. Events keep the raw values (enums, amounts, timers) so
+//! callers can analyse or export them; `format_log` turns them into the
+//! pre-formatted `LogEntry` rows the UI's log table shows.
+
+use serde::{Serialize, Deserialize};
+use crate::constants::{self as C, Action, Buff, DamageSource};
+use crate::orchestration::{LogEntry, LogType, SpellResult};
+use crate::state::{Boss, MageLane};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EventKind {
+    #[default]
+    CastStart,
+    CastSuccess,
+    SpellImpact,
+    /// Pyroblast DoT tick
+    PeriodicDamage,
+    IgniteTick,
+}
+
+/// Buffs and combustion state of the acting lane
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct LaneAuras {
+    pub sapp: bool,
+    pub toep: bool,
+    pub zhc: bool,
+    pub mqg: bool,
+    pub power_infusion: bool,
+    pub arcane_power: bool,
+    pub presence_of_mind: bool,
+    pub berserking: bool,
+    pub combustion_stacks: u8,
+    pub combustion_charges: u8,
+    pub combustion_cooldown: f64,
+}
+
+impl LaneAuras {
+    pub fn of(l: &MageLane) -> Self {
+        Self {
+            sapp: l.buff_timer[Buff::Sapp as usize] > 0.0,
+            toep: l.buff_timer[Buff::Toep as usize] > 0.0,
+            zhc: l.buff_timer[Buff::Zhc as usize] > 0.0,
+            mqg: l.buff_timer[Buff::Mqg as usize] > 0.0,
+            power_infusion: l.pi_timer.iter().any(|&t| t > 0.0),
+            arcane_power: l.ap_timer > 0.0,
+            presence_of_mind: l.pom_active,
+            berserking: l.berserk_timer > 0.0,
+            combustion_stacks: l.comb_stack,
+            combustion_charges: l.comb_left,
+            combustion_cooldown: l.comb_cooldown.max(0.0),
+        }
+    }
+}
+
+/// Debuffs on the boss; stacks are 0 once a debuff has fallen off
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct TargetDebuffs {
+    pub scorch_stacks: u8,
+    pub scorch_remaining: f64,
+    pub ignite_stacks: u8,
+    pub ignite_remaining: f64,
+    pub ignite_value: f64,
+    pub ignite_multiplier: f64,
+    pub wc_stacks: u8,
+    pub wc_remaining: f64,
+    pub dragonling: bool,
+    pub nightfall_remaining: f64,
+}
+
+impl TargetDebuffs {
+    pub fn of(b: &Boss, now: f64) -> Self {
+        let scorch = b.scorch_timer > 0.0;
+        let ignite = b.ignite_timer > 0.0;
+        let wc = b.wc_timer > 0.0;
+        Self {
+            scorch_stacks: if scorch { b.scorch_count } else { 0 },
+            scorch_remaining: b.scorch_timer.max(0.0),
+            ignite_stacks: if ignite { b.ignite_count } else { 0 },
+            ignite_remaining: b.ignite_timer.max(0.0),
+            ignite_value: if ignite { b.ignite_value } else { 0.0 },
+            ignite_multiplier: if ignite { b.ignite_multiplier } else { 0.0 },
+            wc_stacks: if wc { b.wc_count } else { 0 },
+            wc_remaining: b.wc_timer.max(0.0),
+            dragonling: now >= b.dragonling_start && now < b.dragonling_start + C::DRAGONLING_DURATION,
+            nightfall_remaining: b.spell_vulnerability.max(0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CombatEvent {
+    pub t: f64,
+    pub kind: EventKind,
+    /// Acting lane; for ignite ticks the lane that owns the ignite
+    pub lane: Option<usize>,
+    /// Set on casts
+    pub action: Option<Action>,
+    /// Set on impacts and ticks
+    pub source: Option<DamageSource>,
+    pub amount: f64,
+    /// Damage multiplier left after partial resists (1.0 = nothing resisted)
+    pub partial: f64,
+    pub result: SpellResult,
+    /// Running raid / ignite DPS when the event happened
+    pub raid_dps: f64,
+    pub ignite_dps: f64,
+    /// Acting lane's buffs; None for ignite ticks
+    pub auras: Option<LaneAuras>,
+    pub debuffs: TargetDebuffs,
+}
+
+fn combustion_string(a: &LaneAuras) -> String {
+    if a.combustion_charges > 0 {
+        return format!("stack: {}  remain: {}", a.combustion_stacks, a.combustion_charges);
+    } else if a.combustion_cooldown <= 0.0 {
+        return "off cooldown".to_string();
+    }
+    format!("cooldown: {:.2}", a.combustion_cooldown)
+}
+
+fn buff_string(a: &LaneAuras) -> String {
+    let flags = [
+        (a.sapp, "Sapp", "    "),
+        (a.toep, "Toep", "    "),
+        (a.zhc, "ZHC", "   "),
+        (a.mqg, "MQG", "   "),
+        (a.power_infusion, "PI", "  "),
+        (a.arcane_power, "AP", "  "),
+        (a.presence_of_mind, "PoM", "  "),
+        (a.berserking, "BSK", "  "),
+    ];
+    flags.iter().map(|&(on, name, blank)| if on { name } else { blank }).collect()
+}
+
+fn debuff_string(d: &TargetDebuffs) -> String {
+    let mut debuffs = String::new();
+    if d.scorch_stacks > 0 {
+        debuffs.push_str(&format!("scorch:{}({:.2}) ", d.scorch_stacks, d.scorch_remaining));
+    } else {
+        debuffs.push_str("scorch:0 ");
+    }
+    if d.ignite_stacks > 0 {
+        debuffs.push_str(&format!("ignite:{}({:.2})[{:.0}|{:.2}] ", d.ignite_stacks, d.ignite_remaining, d.ignite_value, d.ignite_multiplier));
+    } else {
+        debuffs.push_str("ignite:0 ");
+    }
+    if d.wc_stacks > 0 {
+        debuffs.push_str(&format!("wc:{}({:.2}) ", d.wc_stacks, d.wc_remaining));
+    } // no else.  this is a fire simulator
+    if d.dragonling {
+        debuffs.push_str("dragonling ");
+    }
+    if d.nightfall_remaining > 0.0 {
+        debuffs.push_str(&format!("nightfall:{:.2}", d.nightfall_remaining));
+    }
+    debuffs
+}
+
+/// Render events as the text rows of the UI log; `names` are the lane names
+pub fn format_log(events: &[CombatEvent], names: &[String]) -> Vec<LogEntry> {
+    events.iter().map(|e| {
+        let unit_name = match (e.kind, e.lane) {
+            (EventKind::IgniteTick, _) | (_, None) => String::new(),
+            (_, Some(lane)) => names.get(lane).cloned().unwrap_or_default(),
+        };
+        let (log_type, text) = match e.kind {
+            EventKind::CastStart | EventKind::CastSuccess => {
+                let action = e.action.map(|a| a.to_string()).unwrap_or_default();
+                let log_type = if e.kind == EventKind::CastStart { LogType::CastStart } else { LogType::CastSuccess };
+                (log_type, format!("s[{}]", action))
+            }
+            EventKind::SpellImpact | EventKind::PeriodicDamage => {
+                let source = e.source.map(|s| s.to_string()).unwrap_or_default();
+                (LogType::SpellImpact, format!("s[{}] -> t[{:.0}]", source, e.amount))
+            }
+            EventKind::IgniteTick => (LogType::IgniteTick, format!("a[Ignite] -> t[{:.0}]", e.amount)),
+        };
+        LogEntry {
+            log_type,
+            unit_name,
+            text,
+            t: e.t,
+            dps: 0.0,
+            total_dps: e.raid_dps,
+            ignite_dps: e.ignite_dps,
+            value: e.amount,
+            value2: (1.0 - e.partial) * e.amount,
+            spell_result: e.result,
+            combustion: e.auras.as_ref().map(combustion_string).unwrap_or_default(),
+            buffs: e.auras.as_ref().map(buff_string).unwrap_or_default(),
+            debuffs: debuff_string(&e.debuffs),
+        }
+    }).collect()
+}
@@ -0,0 +1,119 @@
+//! grading.rs — grade every decision of an APL with branch rollouts
+//!
+//! Each sampled fight is played with the APL. Before every decision the fight is
+//! forked and each legal action of the deciding mage is rolled out (see `branch.rs`).
+//! A decision is flagged when the action the APL picked loses more than the
+//! threshold against the best alternative, clearly beyond rollout noise. Flags are
+//! then attributed to the opener step or priority rule that made the pick.
+
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use crate::branch::compare_actions;
+use crate::constants::Action;
+use crate::decisions::{legal_actions, Decider, DecisionSource};
+use crate::orchestration::{iteration_seed, SimParams, Simulation};
+use crate::stats::Estimate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GradeOptions {
+    /// Fights to sample, iterations 1..=fights
+    pub fights: u32,
+    /// Rollouts per candidate action
+    pub rollouts: usize,
+    /// Damage a pick may lose against the best action before it is flagged
+    pub threshold: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GradedDecision {
+    pub fight: u64,
+    pub time: f64,
+    pub lane: usize,
+    pub rule: Option<DecisionSource>,
+    pub chosen: Action,
+    pub best: Action,
+    /// Damage lost against `best`
+    pub loss: Estimate,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleGrade {
+    pub lane: usize,
+    pub rule: Option<DecisionSource>,
+    pub action: Action,
+    pub decisions: usize,
+    pub flagged: usize,
+    /// Mean loss over all decisions the rule made, flagged or not
+    pub mean_loss: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AplGrade {
+    pub fights: u32,
+    pub rollouts: usize,
+    pub threshold: f64,
+    pub decisions: usize,
+    /// Flagged decisions, biggest loss first
+    pub flagged: Vec<GradedDecision>,
+    /// Rules by how often they were flagged, then by mean loss
+    pub rules: Vec<RuleGrade>,
+}
+
+/// Grade every decision `make_decider()` takes over `options.fights` fights
+pub fn grade_decisions<D, F>(params: &SimParams, make_decider: F, seed: u64, options: GradeOptions) -> AplGrade
+where
+    D: Decider + Clone,
+    F: Fn() -> D,
+{
+    let mut decisions = 0;
+    let mut flagged = Vec::new();
+    // mean_loss holds the loss sum until the end
+    let mut rules: BTreeMap<(usize, Option<DecisionSource>), RuleGrade> = BTreeMap::new();
+
+    for fight in 1..=options.fights as u64 {
+        let mut sim = Simulation::new(params, seed, fight, false).without_metrics();
+        let mut decider = make_decider();
+        let rollout_seed = iteration_seed(seed, fight);
+
+        while sim.in_progress() {
+            let Some(lane) = sim.next_lane() else { break };
+            let mut candidates = legal_actions(&sim.st, lane);
+            let chosen = decider.clone().next_action(&sim.st).filter(|&(l, _, _)| l == lane).map(|(_, a, _)| a);
+            if let Some(a) = chosen.filter(|a| !candidates.contains(a)) { candidates.push(a); }
+
+            let comparison = compare_actions(&sim, &decider, lane, &candidates, params.timing.recast_delay, options.rollouts, rollout_seed);
+            sim.step(&mut decider);
+
+            let (Some(chosen), Some(comparison)) = (chosen, comparison) else { continue };
+            let Some(outcome) = comparison.outcomes.iter().find(|o| o.action == chosen) else { continue };
+            let rule = decider.last_rule(lane);
+            let loss = outcome.loss;
+            let is_flagged = loss.mean > options.threshold && loss.mean > 2.0 * loss.std_error;
+
+            decisions += 1;
+            let grade = rules.entry((lane, rule))
+                .or_insert(RuleGrade { lane, rule, action: chosen, decisions: 0, flagged: 0, mean_loss: 0.0 });
+            grade.decisions += 1;
+            grade.mean_loss += loss.mean;
+            if is_flagged {
+                grade.flagged += 1;
+                flagged.push(GradedDecision { fight, time: comparison.time, lane, rule, chosen, best: comparison.best, loss });
+            }
+        }
+    }
+
+    flagged.sort_by(|a, b| b.loss.mean.total_cmp(&a.loss.mean));
+    let mut rules: Vec<RuleGrade> = rules.into_values()
+        .map(|g| RuleGrade { mean_loss: g.mean_loss / g.decisions as f64, ..g })
+        .collect();
+    rules.sort_by(|a, b| b.flagged.cmp(&a.flagged).then(b.mean_loss.total_cmp(&a.mean_loss)).then(a.lane.cmp(&b.lane)).then(a.rule.cmp(&b.rule)));
+
+    AplGrade {
+        fights: options.fights,
+        rollouts: options.rollouts,
+        threshold: options.threshold,
+        decisions,
+        flagged,
+        rules,
+    }
+}
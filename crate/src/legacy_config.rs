@@ -1,11 +1,13 @@
 use std::collections::HashMap;
 use crate::constants::{
-    Racial, Buff, BossType,
+    self as C, Racial, Buff, BossType,
     ConsumeBuff as Cn, RaidBuff as Rd, WorldBuff as Wb,
     TeamTalentPoints,
 };
 use crate::orchestration::Buffs; // <- your Buffs struct
 use crate::orchestration::{SimParams, Stats, Timing, Configuration};
+use crate::stat_weights::StatStep;
+use crate::stats::DEFAULT_PERCENTILES;
 use strum::IntoEnumIterator;
 use serde::Deserialize;
 use serde_json::Value;
@@ -30,6 +32,15 @@ pub struct LegacyConfig {
     pub players: Vec<LegacyPlayer>,
     // Optional RNG seed if UI sends it; fallback to host seed
     pub rng_seed: Option<u64>,
+    // Optional stat-weight perturbations; defaults to +15 SP / +1.5% crit / -1.5% hit
+    pub stat_weight_steps: Option<Vec<StatStep>>,
+    // Optional stop rule: relative standard error of raid DPS (e.g. 0.002 = 0.2%)
+    pub target_rel_error: Option<f64>,
+    // Optional percentiles (0..=100) to report per player
+    pub percentiles: Option<Vec<f64>>,
+    // Optional: keep logs of the min / median / max iterations, plus one percentile
+    pub representative_logs: Option<bool>,
+    pub log_percentile: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -283,6 +294,12 @@ fn convert_legacy_to_simparams_internal(cfg: LegacyConfig, timing: Timing) -> Si
         coe: coe,
         talents: talents,
         name: name,
+        rng_seed: cfg.rng_seed.unwrap_or(C::DEFAULT_RNG_SEED),
+        stat_steps: cfg.stat_weight_steps.clone().unwrap_or_else(StatStep::legacy),
+        target_rel_error: cfg.target_rel_error,
+        percentiles: cfg.percentiles.clone().unwrap_or_else(|| DEFAULT_PERCENTILES.to_vec()),
+        representative_logs: cfg.representative_logs.unwrap_or(false),
+        log_percentile: cfg.log_percentile,
     };
 
     SimParams { stats, buffs, timing, config }
@@ -6,13 +6,25 @@ pub mod decisions;
 pub mod orchestration;
 pub mod legacy_config; // if you added it
 pub mod apl;
+pub mod rng;
+pub mod stats;
+pub mod stat_weights;
+pub mod metrics;
+pub mod events;
+pub mod combat_log;
+pub mod replay;
+pub mod branch;
+pub mod grading;
+pub mod tuning;
+pub mod apl_search;
 
 use wasm_bindgen::prelude::*;
 use serde_wasm_bindgen::{from_value, to_value};
-use crate::orchestration::{run_single, run_many_with, SimulationResult, SimulationsResult};
+use crate::orchestration::{run_single, run_many_with, run_chunk_with, run_until_converged, representative_logs, Convergence, SimAccumulator, SimParams, SimulationResult, SimulationsResult};
 use crate::legacy_config::{LegacyConfig, convert_legacy_to_simparams_and_players_data};
 use crate::decisions::TeamDecider;
 use crate::apl::create_team_decider_from_apls;
+use crate::events::format_log;
 use console_error_panic_hook;
 use log::{Level};
 
@@ -32,60 +44,57 @@ pub fn run_simulations(cfg_js: JsValue, iterations: i32) -> JsValue {
     };
 
 
-    let seed = 42u64; // or take from legacy.rng_seed.unwrap_or(42)
-    let mut results: SimulationsResult = run_many_with::<_, _>(&params, &make_decider, iterations, seed);
+    let seed = params.config.rng_seed;
+    let results: SimulationsResult = match params.config.target_rel_error {
+        // `iterations` becomes the cap when a target error is given
+        Some(rel) => run_until_converged(&params, make_decider, Convergence::new(rel, iterations), seed),
+        None => run_many_with::<_, _>(&params, &make_decider, iterations, seed),
+    };
+    //log::debug!("Engine made it THIS!!!!! far. {:?}", results);
 
-    if params.config.target.len() > 0 && params.config.vary.len() > 0 && params.config.do_stat_weights {
-        let mut sp_params = params.clone();
-        for (idx, (fire_sp, frost_sp)) in sp_params.stats.fire_power.iter_mut()
-            .zip(sp_params.stats.frost_power.iter_mut())
-            .enumerate() {
-            if sp_params.config.vary.iter().any(|&i| i == idx) {
-                *fire_sp += 15.0;
-                *frost_sp += 15.0;
-            }
-        }
-        let results_sp: SimulationsResult = run_many_with::<_, _>(&sp_params, &make_decider, iterations, seed);
+    serde_wasm_bindgen::to_value(&results).unwrap()
+}
 
-        let mut crit_params = params.clone();
-        for (idx, crit) in crit_params.stats.crit_chance.iter_mut().enumerate() {
-            if crit_params.config.vary.iter().any(|&i| i == idx) {
-                *crit += 0.015;
-            }
-        }
-        let results_crit: SimulationsResult = run_many_with::<_, _>(&crit_params, &make_decider, iterations, seed);
+/// Run iterations `first_iteration..first_iteration + iterations` and return the raw
+/// accumulator, to be combined with `merge_simulation_chunks`
+#[wasm_bindgen]
+pub fn run_simulation_chunk(cfg_js: JsValue, first_iteration: u32, iterations: i32) -> JsValue {
+    let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+    let (params, players_data) = convert_legacy_to_simparams_and_players_data(legacy);
+    let make_decider = || create_team_decider_from_apls(&players_data, &params.timing);
 
-        let mut hit_params = params.clone();
-        for (idx, hit) in hit_params.stats.hit_chance.iter_mut().enumerate() {
-            if hit_params.config.vary.iter().any(|&i| i == idx) {
-                *hit -= 0.015;
-            }
-        }
-        let results_hit: SimulationsResult = run_many_with::<_, _>(&hit_params, &make_decider, iterations, seed);
-
-        let target_players = params.config.target.len();
-        results.dps_select = 0.0;
-        results.dps_sp = 0.0;
-        results.dps_crit = 0.0;
-        results.dps_hit = 0.0;
-        results.dps90_select = 0.0;
-        results.dps90_sp = 0.0;
-        results.dps90_crit = 0.0;
-        results.dps90_hit = 0.0;
-        for idx  in params.config.target.iter() {
-            results.dps_select += (iterations as f64) * results.players[*idx as usize].dps / (target_players as f64);
-            results.dps_sp += (iterations as f64) * results_sp.players[*idx as usize].dps / (target_players as f64);
-            results.dps_crit += (iterations as f64) * results_crit.players[*idx as usize].dps / (target_players as f64);
-            results.dps_hit += (iterations as f64) * results_hit.players[*idx as usize].dps / (target_players as f64);
-            results.dps90_select += (iterations as f64) * results.players[*idx as usize].ninetieth / (target_players as f64);
-            results.dps90_sp += (iterations as f64) * results_sp.players[*idx as usize].ninetieth / (target_players as f64);
-            results.dps90_crit += (iterations as f64) * results_crit.players[*idx as usize].ninetieth / (target_players as f64);
-            results.dps90_hit += (iterations as f64) * results_hit.players[*idx as usize].ninetieth / (target_players as f64);
-        }
+    let acc = run_chunk_with(&params, make_decider, params.config.rng_seed, first_iteration as u64, iterations);
+    to_value(&acc).unwrap()
+}
+
+/// Merge an array of chunk accumulators of `cfg_js` into a final `SimulationsResult`,
+/// with representative logs if the config asks for them
+#[wasm_bindgen]
+pub fn merge_simulation_chunks(cfg_js: JsValue, chunks_js: JsValue) -> JsValue {
+    let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+    let (params, players_data) = convert_legacy_to_simparams_and_players_data(legacy);
+    let make_decider = || create_team_decider_from_apls(&players_data, &params.timing);
+
+    let chunks: Vec<SimAccumulator> = from_value(chunks_js).expect("bad chunks from JS");
+    let mut acc = SimAccumulator::new(&params.config);
+    for chunk in &chunks {
+        acc.merge(chunk);
     }
-    //log::debug!("Engine made it THIS!!!!! far. {:?}", results);
+    let logs = representative_logs(&params, make_decider, params.config.rng_seed, &acc);
+    let mut results = acc.finish();
+    results.logs = logs;
+    to_value(&results).unwrap()
+}
 
-    serde_wasm_bindgen::to_value(&results).unwrap()
+/// Per-mage stat weights: every mage is varied in turn and every mage is measured
+#[wasm_bindgen]
+pub fn run_stat_weight_matrix(cfg_js: JsValue, iterations: i32) -> JsValue {
+    let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+    let (params, players_data) = convert_legacy_to_simparams_and_players_data(legacy);
+    let make_decider = || create_team_decider_from_apls(&players_data, &params.timing);
+
+    let matrix = stat_weights::run_stat_weight_matrix(&params, make_decider, iterations, params.config.rng_seed, &params.config.stat_steps);
+    to_value(&matrix).unwrap()
 }
 
 #[wasm_bindgen]
@@ -100,8 +109,9 @@ pub fn run_simulation(cfg_js: JsValue) -> JsValue {
     let mut decider: TeamDecider = create_team_decider_from_apls(&players_data, &params.timing);    
     //let make_decider = move || create_decider_from_players(players_data);    
 
-    let seed = 9u64; // or take from legacy.rng_seed.unwrap_or(42)
-    let result: SimulationResult = run_single(&params, &mut decider, seed, 0);
+    let seed = params.config.rng_seed;
+    let mut result: SimulationResult = run_single(&params, &mut decider, seed, 0);
+    result.log = format_log(&result.events, &params.config.name);
 
     // // Aggregate like the old UI expects
     // let m = params.config.num_mages as f64;
@@ -118,4 +128,161 @@ pub fn run_simulation(cfg_js: JsValue) -> JsValue {
     //log::debug!("Engine made it THIS!!!!! far. {:?}", result);
 
     to_value(&result).unwrap()
-}
\ No newline at end of file
+}
+
+/// Runs the logged iteration and returns it as `WoWCombatLog.txt` text
+#[wasm_bindgen]
+pub fn export_combat_log(cfg_js: JsValue) -> String {
+    console_error_panic_hook::set_once();
+
+    let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+    let (params, players_data) = convert_legacy_to_simparams_and_players_data(legacy);
+    let mut decider: TeamDecider = create_team_decider_from_apls(&players_data, &params.timing);
+
+    let result = run_single(&params, &mut decider, params.config.rng_seed, 0);
+    combat_log::export_combat_log(&result.events, &params.config.name, params.buffs.boss, result.t)
+}
+
+/// Fork the logged iteration at the first decision at or after `t` and compare every
+/// legal action of the deciding mage over `rollouts` continuations
+#[wasm_bindgen]
+pub fn compare_decision(cfg_js: JsValue, t: f64, rollouts: u32) -> JsValue {
+    console_error_panic_hook::set_once();
+
+    let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+    let (params, players_data) = convert_legacy_to_simparams_and_players_data(legacy);
+    let decider = create_team_decider_from_apls(&players_data, &params.timing);
+
+    let comparison = branch::branch_at_time(&params, decider, params.config.rng_seed, 0, t, rollouts as usize);
+    to_value(&comparison).unwrap()
+}
+
+/// Grade every APL decision over `fights` fights with `rollouts` rollouts per action;
+/// picks losing more than `threshold` damage to the best action are flagged
+#[wasm_bindgen]
+pub fn grade_apl(cfg_js: JsValue, fights: u32, rollouts: u32, threshold: f64) -> JsValue {
+    console_error_panic_hook::set_once();
+
+    let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+    let (params, players_data) = convert_legacy_to_simparams_and_players_data(legacy);
+    let make_decider = || create_team_decider_from_apls(&players_data, &params.timing);
+
+    let options = grading::GradeOptions { fights, rollouts: rollouts as usize, threshold };
+    to_value(&grading::grade_decisions(&params, make_decider, params.config.rng_seed, options)).unwrap()
+}
+
+/// Tune APL `Const` thresholds; `spec_js` is a `tuning::TuneSpec`
+#[wasm_bindgen]
+pub fn tune_apl(cfg_js: JsValue, spec_js: JsValue) -> JsValue {
+    console_error_panic_hook::set_once();
+
+    let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+    let (params, players_data) = convert_legacy_to_simparams_and_players_data(legacy);
+    let spec: tuning::TuneSpec = from_value(spec_js).expect("bad tuning spec from JS");
+
+    to_value(&tuning::tune_apl(&params, &players_data, &spec, params.config.rng_seed)).unwrap()
+}
+
+/// Search APL structure (drop, reorder, swap actions); `spec_js` is an `apl_search::SearchSpec`
+#[wasm_bindgen]
+pub fn search_apl(cfg_js: JsValue, spec_js: JsValue) -> JsValue {
+    console_error_panic_hook::set_once();
+
+    let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+    let (params, players_data) = convert_legacy_to_simparams_and_players_data(legacy);
+    let spec: apl_search::SearchSpec = from_value(spec_js).expect("bad search spec from JS");
+
+    to_value(&apl_search::search_apl(&params, &players_data, &spec, params.config.rng_seed)).unwrap()
+}
+
+/// Re-run the mage casts of a `WoWCombatLog.txt` `iterations` times with fresh rolls
+#[wasm_bindgen]
+pub fn replay_combat_log(cfg_js: JsValue, log_text: String, iterations: i32) -> JsValue {
+    console_error_panic_hook::set_once();
+
+    let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+    let (params, _) = convert_legacy_to_simparams_and_players_data(legacy);
+
+    let timeline = replay::parse_combat_log(&log_text, &params.config.name);
+    let report = replay::run_replay(&params, &timeline, iterations, params.config.rng_seed);
+    to_value(&report).unwrap()
+}
+
+/// A run the worker drives in chunks, so it can report progress and stop early.
+#[wasm_bindgen]
+pub struct SimSession {
+    params: SimParams,
+    players_data: Vec<Option<serde_json::Value>>,
+    iterations: i32,
+    next_iteration: u64,
+    acc: SimAccumulator,
+}
+
+impl SimSession {
+    /// The target error is set and reached, whether or not the cap was hit too
+    fn converged(&self) -> bool {
+        match self.params.config.target_rel_error {
+            Some(rel) => self.acc.iterations() >= Convergence::new(rel, self.iterations).min_iterations
+                && self.acc.relative_error() <= rel,
+            None => false,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SimSession {
+    /// Runs iterations from `first_iteration` on, so sessions on several workers can
+    /// split one run; `iterations` is the planned total, or the cap when a target error is set
+    #[wasm_bindgen(constructor)]
+    pub fn new(cfg_js: JsValue, first_iteration: u32, iterations: i32) -> SimSession {
+        let legacy: LegacyConfig = from_value(cfg_js).expect("bad config from JS");
+        let (params, players_data) = convert_legacy_to_simparams_and_players_data(legacy);
+        let acc = SimAccumulator::new(&params.config);
+        SimSession { params, players_data, iterations, next_iteration: first_iteration as u64, acc }
+    }
+
+    /// Run up to `n` more iterations; returns how many were run
+    pub fn run_chunk(&mut self, n: i32) -> i32 {
+        let n = n.min(self.iterations - self.acc.iterations()).max(0);
+        if self.is_done() { return 0; }
+
+        let chunk = run_chunk_with(&self.params, || create_team_decider_from_apls(&self.players_data, &self.params.timing),
+            self.params.config.rng_seed, self.next_iteration, n);
+        self.acc.merge(&chunk);
+        self.next_iteration += n as u64;
+        n
+    }
+
+    /// Iterations completed so far
+    pub fn progress(&self) -> i32 { self.acc.iterations() }
+
+    pub fn total(&self) -> i32 { self.iterations }
+
+    /// All planned iterations run, or the target error reached
+    pub fn is_done(&self) -> bool {
+        self.acc.iterations() >= self.iterations || self.converged()
+    }
+
+    /// Results over the iterations run so far
+    pub fn partial(&self) -> JsValue {
+        to_value(&self.acc.clone().finish()).unwrap()
+    }
+
+    /// The raw accumulator of the iterations run so far, to be combined with the other
+    /// sessions of a split run by `merge_simulation_chunks`
+    pub fn accumulator(&self) -> JsValue {
+        to_value(&self.acc).unwrap()
+    }
+
+    /// Final results; representative logs, if requested, are run over the completed
+    /// iterations
+    pub fn finish(self) -> JsValue {
+        let converged = self.converged();
+        let make_decider = || create_team_decider_from_apls(&self.players_data, &self.params.timing);
+        let logs = representative_logs(&self.params, make_decider, self.params.config.rng_seed, &self.acc);
+        let mut results = self.acc.finish();
+        results.converged = converged;
+        results.logs = logs;
+        to_value(&results).unwrap()
+    }
+}
@@ -0,0 +1,626 @@
+//! metrics.rs — per-iteration combat metrics collected while the state steps
+//!
+//! `State` records into a `Metrics` as events happen; the orchestration layer sums
+//! them over iterations and turns the totals into report tables.
+
+use serde::{Serialize, Deserialize};
+use crate::constants::{self as C, Action, DamageSource, DAMAGE_SOURCES, NUM_DAMAGE_SOURCES};
+use crate::stats::{RunningStats, Summary};
+
+/// Counts and damage of one source on one lane
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct SourceStats {
+    pub casts: u64,
+    pub hits: u64,
+    pub crits: u64,
+    pub misses: u64,
+    /// Landed hits by partial resist: 0%, 25%, 50%, 75% resisted
+    pub resists: [u64; 4],
+    pub hit_damage: f64,
+    pub crit_damage: f64,
+    pub max_hit: f64,
+}
+
+impl SourceStats {
+    pub fn damage(&self) -> f64 { self.hit_damage + self.crit_damage }
+
+    pub fn merge(&mut self, other: &SourceStats) {
+        self.casts += other.casts;
+        self.hits += other.hits;
+        self.crits += other.crits;
+        self.misses += other.misses;
+        for (a, b) in self.resists.iter_mut().zip(other.resists.iter()) { *a += b; }
+        self.hit_damage += other.hit_damage;
+        self.crit_damage += other.crit_damage;
+        self.max_hit = self.max_hit.max(other.max_hit);
+    }
+}
+
+/// Cooldowns whose usage is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Cooldown { Combustion = 0, Sapp = 1, Toep = 2, Zhc = 3, Mqg = 4, PowerInfusion = 5, ArcanePower = 6, PresenceOfMind = 7, Berserking = 8 }
+
+pub const NUM_COOLDOWNS: usize = 9;
+pub const COOLDOWNS: [Cooldown; NUM_COOLDOWNS] = [
+    Cooldown::Combustion, Cooldown::Sapp, Cooldown::Toep, Cooldown::Zhc, Cooldown::Mqg,
+    Cooldown::PowerInfusion, Cooldown::ArcanePower, Cooldown::PresenceOfMind, Cooldown::Berserking,
+];
+pub const COOLDOWN_NAMES: [&str; NUM_COOLDOWNS] = [
+    "Combustion", "Essence of Sapphiron", "Ephemeral Power", "Unstable Power", "Mind Quickening",
+    "Power Infusion", "Arcane Power", "Presence of Mind", "Berserking",
+];
+
+impl Cooldown {
+    /// Cooldown started by casting `action`, if any
+    pub fn from_action(action: Action) -> Option<Cooldown> {
+        match action {
+            Action::Combustion => Some(Cooldown::Combustion),
+            Action::Sapp => Some(Cooldown::Sapp),
+            Action::Toep => Some(Cooldown::Toep),
+            Action::Zhc => Some(Cooldown::Zhc),
+            Action::Mqg => Some(Cooldown::Mqg),
+            Action::PowerInfusion => Some(Cooldown::PowerInfusion),
+            Action::ArcanePower => Some(Cooldown::ArcanePower),
+            Action::PresenceOfMind => Some(Cooldown::PresenceOfMind),
+            Action::Berserking => Some(Cooldown::Berserking),
+            _ => None,
+        }
+    }
+}
+
+/// Uses of one cooldown on one lane
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct CooldownUsage {
+    pub uses: u32,
+    pub first_use: f64,
+    pub last_use: f64,
+    /// Sum of gaps between consecutive uses
+    pub interval_sum: f64,
+    /// Seconds the cooldown sat ready before each use, summed
+    pub delay_sum: f64,
+    /// Charges or procs left unspent at fight end (Combustion, Presence of Mind)
+    pub wasted: u32,
+    /// Seconds the cooldown sat ready at the end without being used
+    pub ready_at_end: f64,
+}
+
+impl CooldownUsage {
+    pub fn record_use(&mut self, time: f64, delay: f64) {
+        if self.uses == 0 {
+            self.first_use = time;
+        } else {
+            self.interval_sum += time - self.last_use;
+        }
+        self.uses += 1;
+        self.last_use = time;
+        self.delay_sum += delay;
+    }
+
+    pub fn merge(&mut self, other: &CooldownUsage) {
+        if other.uses > 0 && (self.uses == 0 || other.first_use < self.first_use) {
+            self.first_use = other.first_use;
+        }
+        self.uses += other.uses;
+        self.last_use = self.last_use.max(other.last_use);
+        self.interval_sum += other.interval_sum;
+        self.delay_sum += other.delay_sum;
+        self.wasted += other.wasted;
+        self.ready_at_end += other.ready_at_end;
+    }
+}
+
+/// What a lane is doing at a point of the fight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Activity {
+    /// Cast bar running
+    Casting = 0,
+    /// Leftover global cooldown after a cast or instant
+    GcdLock = 1,
+    /// Reaction delay before the next cast starts
+    Reaction = 2,
+    /// Waiting by choice, or before the first action
+    Idle = 3,
+}
+
+pub const NUM_ACTIVITIES: usize = 4;
+pub const ACTIVITY_NAMES: [&str; NUM_ACTIVITIES] = ["Casting", "GCD", "Reaction", "Idle"];
+
+/// One stretch of a lane's timeline, for the logged iteration
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ActivityInterval {
+    pub lane: usize,
+    pub activity: Activity,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LaneMetrics {
+    pub sources: [SourceStats; NUM_DAMAGE_SOURCES],
+    pub cooldowns: [CooldownUsage; NUM_COOLDOWNS],
+    /// Seconds spent in each `Activity`
+    pub activity: [f64; NUM_ACTIVITIES],
+}
+
+/// Per-lane buffs whose uptime is tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Aura { Sapp = 0, Toep = 1, Zhc = 2, Mqg = 3, PowerInfusion = 4, ArcanePower = 5, Berserking = 6, Combustion = 7 }
+
+pub const NUM_AURAS: usize = 8;
+pub const AURA_NAMES: [&str; NUM_AURAS] = [
+    "Essence of Sapphiron", "Ephemeral Power", "Unstable Power", "Mind Quickening",
+    "Power Infusion", "Arcane Power", "Berserking", "Combustion",
+];
+
+const SCORCH_SLOTS: usize = C::SCORCH_STACK as usize + 1;
+const IGNITE_SLOTS: usize = C::IGNITE_STACK as usize + 1;
+
+/// Seconds of the fight each aura was up; debuff arrays are indexed by stack count
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Uptime {
+    pub scorch: [f64; SCORCH_SLOTS],
+    pub ignite: [f64; IGNITE_SLOTS],
+    pub winters_chill: f64,
+    pub nightfall: f64,
+    pub dragonling: f64,
+    pub lanes: Vec<[f64; NUM_AURAS]>,
+}
+
+impl Uptime {
+    pub fn new(num_lanes: usize) -> Self {
+        Self { lanes: vec![[0.0; NUM_AURAS]; num_lanes], ..Default::default() }
+    }
+
+    pub fn merge(&mut self, other: &Uptime) {
+        for (a, b) in self.scorch.iter_mut().zip(other.scorch.iter()) { *a += b; }
+        for (a, b) in self.ignite.iter_mut().zip(other.ignite.iter()) { *a += b; }
+        self.winters_chill += other.winters_chill;
+        self.nightfall += other.nightfall;
+        self.dragonling += other.dragonling;
+        if self.lanes.len() < other.lanes.len() {
+            self.lanes.resize(other.lanes.len(), [0.0; NUM_AURAS]);
+        }
+        for (a, b) in self.lanes.iter_mut().zip(other.lanes.iter()) {
+            for (x, y) in a.iter_mut().zip(b.iter()) { *x += y; }
+        }
+    }
+}
+
+/// Ignite life cycle events of one iteration (or summed over several)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct IgniteMetrics {
+    /// Ignites started from zero stacks
+    pub ignites: u64,
+    /// Ignites that ran out before the fight ended, and seconds until the next one
+    pub drops: u64,
+    pub drop_time: f64,
+    /// Crits on a 5-stack ignite: timer refreshed, no stack added
+    pub cap_refreshes: u64,
+    /// Crits that refreshed an ignite with under `DECISION_POINT` seconds left
+    pub late_refreshes: u64,
+    /// New ignites started by a different mage than the previous one
+    pub owner_changes: u64,
+    pub ticks: u64,
+    pub tick_damage: f64,
+    pub resisted_ticks: u64,
+}
+
+impl IgniteMetrics {
+    pub fn record_drop(&mut self, duration: f64) {
+        self.drops += 1;
+        self.drop_time += duration;
+    }
+
+    pub fn record_tick(&mut self, damage: f64, partial: f64) {
+        self.ticks += 1;
+        self.tick_damage += damage;
+        if partial < 1.0 { self.resisted_ticks += 1; }
+    }
+
+    pub fn merge(&mut self, other: &IgniteMetrics) {
+        self.ignites += other.ignites;
+        self.drops += other.drops;
+        self.drop_time += other.drop_time;
+        self.cap_refreshes += other.cap_refreshes;
+        self.late_refreshes += other.late_refreshes;
+        self.owner_changes += other.owner_changes;
+        self.ticks += other.ticks;
+        self.tick_damage += other.tick_damage;
+        self.resisted_ticks += other.resisted_ticks;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Metrics {
+    pub lanes: Vec<LaneMetrics>,
+    pub uptime: Uptime,
+    pub ignite: IgniteMetrics,
+}
+
+fn resist_bucket(partial: f64) -> usize {
+    C::RES_AMOUNT.iter().position(|&a| a == partial).unwrap_or(0)
+}
+
+impl Metrics {
+    pub fn new(num_lanes: usize) -> Self {
+        Self { lanes: vec![LaneMetrics::default(); num_lanes], uptime: Uptime::new(num_lanes), ..Default::default() }
+    }
+
+    pub fn record_cast(&mut self, lane: usize, source: DamageSource) {
+        self.lanes[lane].sources[source as usize].casts += 1;
+    }
+
+    pub fn record_miss(&mut self, lane: usize, source: DamageSource) {
+        self.lanes[lane].sources[source as usize].misses += 1;
+    }
+
+    /// A landed hit or tick; `partial` is the damage multiplier from `RES_AMOUNT`
+    pub fn record_damage(&mut self, lane: usize, source: DamageSource, damage: f64, crit: bool, partial: f64) {
+        let s = &mut self.lanes[lane].sources[source as usize];
+        if crit {
+            s.crits += 1;
+            s.crit_damage += damage;
+        } else {
+            s.hits += 1;
+            s.hit_damage += damage;
+        }
+        s.resists[resist_bucket(partial)] += 1;
+        s.max_hit = s.max_hit.max(damage);
+    }
+
+    /// A tick of damage the raid shares, split evenly over the lanes the same way
+    /// `PlayerResult.dps` splits Ignite
+    pub fn record_shared_damage(&mut self, source: DamageSource, damage: f64, partial: f64) {
+        let share = damage / self.lanes.len().max(1) as f64;
+        for lane in 0..self.lanes.len() {
+            self.record_damage(lane, source, share, false, partial);
+        }
+    }
+
+    /// Sum with metrics of other iterations
+    pub fn merge(&mut self, other: &Metrics) {
+        if self.lanes.len() < other.lanes.len() {
+            self.lanes.resize(other.lanes.len(), LaneMetrics::default());
+        }
+        for (a, b) in self.lanes.iter_mut().zip(other.lanes.iter()) {
+            for (sa, sb) in a.sources.iter_mut().zip(b.sources.iter()) { sa.merge(sb); }
+            for (ca, cb) in a.cooldowns.iter_mut().zip(b.cooldowns.iter()) { ca.merge(cb); }
+            for (ta, tb) in a.activity.iter_mut().zip(b.activity.iter()) { *ta += tb; }
+        }
+        self.uptime.merge(&other.uptime);
+        self.ignite.merge(&other.ignite);
+    }
+}
+
+/// One row of a damage-done table, averaged per iteration
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SpellBreakdown {
+    pub source: String,
+    pub casts: f64,
+    pub hits: f64,
+    pub crits: f64,
+    pub misses: f64,
+    /// Fraction of landed hits at 0%, 25%, 50%, 75% resisted
+    pub resists: [f64; 4],
+    pub crit_rate: f64,
+    pub avg_hit: f64,
+    pub avg_crit: f64,
+    pub max_hit: f64,
+    pub damage: f64,
+    pub dps: f64,
+    /// Share of this lane's damage, its even split of ignite included
+    pub share: f64,
+}
+
+/// Damage-done table of one lane from metrics summed over `iterations` runs
+/// lasting `total_time` seconds in all. Sources that never fired are left out.
+pub fn spell_breakdown(lane: &LaneMetrics, iterations: i32, total_time: f64) -> Vec<SpellBreakdown> {
+    let n = iterations.max(1) as f64;
+    let lane_damage: f64 = lane.sources.iter().map(|s| s.damage()).sum();
+
+    DAMAGE_SOURCES.iter().zip(lane.sources.iter())
+        .filter(|(_, s)| s.casts + s.hits + s.crits + s.misses > 0)
+        .map(|(source, s)| {
+            let landed = (s.hits + s.crits) as f64;
+            let per_landed = |x: f64| if landed > 0.0 { x / landed } else { 0.0 };
+            SpellBreakdown {
+                source: source.to_string(),
+                casts: s.casts as f64 / n,
+                hits: s.hits as f64 / n,
+                crits: s.crits as f64 / n,
+                misses: s.misses as f64 / n,
+                resists: s.resists.map(|r| per_landed(r as f64)),
+                crit_rate: per_landed(s.crits as f64),
+                avg_hit: if s.hits > 0 { s.hit_damage / s.hits as f64 } else { 0.0 },
+                avg_crit: if s.crits > 0 { s.crit_damage / s.crits as f64 } else { 0.0 },
+                max_hit: s.max_hit,
+                damage: s.damage() / n,
+                dps: if total_time > 0.0 { s.damage() / total_time } else { 0.0 },
+                share: if lane_damage > 0.0 { s.damage() / lane_damage } else { 0.0 },
+            }
+        })
+        .collect()
+}
+
+/// Uptime of one aura (at one stack count, 0 = any) as a fraction of the fight
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UptimeRow {
+    pub name: String,
+    pub stacks: u8,
+    pub uptime: Summary,
+}
+
+/// Per-iteration uptime fractions; kept apart from the summed `Metrics` so the
+/// report can show the spread across iterations
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UptimeStats {
+    scorch: [RunningStats; SCORCH_SLOTS],
+    ignite: [RunningStats; IGNITE_SLOTS],
+    winters_chill: RunningStats,
+    nightfall: RunningStats,
+    dragonling: RunningStats,
+    lanes: Vec<[RunningStats; NUM_AURAS]>,
+}
+
+impl UptimeStats {
+    /// Add one iteration's uptime for a fight of `duration` seconds
+    pub fn push(&mut self, uptime: &Uptime, duration: f64) {
+        let d = duration.max(1e-9);
+        // slot 0 holds "any stacks"
+        self.scorch[0].push(uptime.scorch[1..].iter().sum::<f64>() / d);
+        for (i, t) in uptime.scorch.iter().enumerate().skip(1) { self.scorch[i].push(t / d); }
+        self.ignite[0].push(uptime.ignite[1..].iter().sum::<f64>() / d);
+        for (i, t) in uptime.ignite.iter().enumerate().skip(1) { self.ignite[i].push(t / d); }
+        self.winters_chill.push(uptime.winters_chill / d);
+        self.nightfall.push(uptime.nightfall / d);
+        self.dragonling.push(uptime.dragonling / d);
+
+        if self.lanes.len() < uptime.lanes.len() {
+            self.lanes.resize(uptime.lanes.len(), [RunningStats::default(); NUM_AURAS]);
+        }
+        for (stats, times) in self.lanes.iter_mut().zip(uptime.lanes.iter()) {
+            for (s, t) in stats.iter_mut().zip(times.iter()) { s.push(t / d); }
+        }
+    }
+
+    pub fn merge(&mut self, other: &UptimeStats) {
+        for (a, b) in self.scorch.iter_mut().zip(other.scorch.iter()) { a.merge(b); }
+        for (a, b) in self.ignite.iter_mut().zip(other.ignite.iter()) { a.merge(b); }
+        self.winters_chill.merge(&other.winters_chill);
+        self.nightfall.merge(&other.nightfall);
+        self.dragonling.merge(&other.dragonling);
+        if self.lanes.len() < other.lanes.len() {
+            self.lanes.resize(other.lanes.len(), [RunningStats::default(); NUM_AURAS]);
+        }
+        for (a, b) in self.lanes.iter_mut().zip(other.lanes.iter()) {
+            for (x, y) in a.iter_mut().zip(b.iter()) { x.merge(y); }
+        }
+    }
+
+    /// Target debuffs; stacked debuffs get an "any stacks" row and one per stack count
+    pub fn debuffs(&self) -> Vec<UptimeRow> {
+        let row = |name: &str, stacks: usize, rs: &RunningStats| UptimeRow { name: name.to_string(), stacks: stacks as u8, uptime: rs.into() };
+        let mut rows = Vec::new();
+        rows.extend(self.scorch.iter().enumerate().map(|(i, rs)| row("Fire Vulnerability", i, rs)));
+        rows.extend(self.ignite.iter().enumerate().map(|(i, rs)| row("Ignite", i, rs)));
+        rows.push(row("Winter's Chill", 0, &self.winters_chill));
+        rows.push(row("Spell Vulnerability", 0, &self.nightfall));
+        rows.push(row("Dragonling", 0, &self.dragonling));
+        rows
+    }
+
+    /// Buffs of one lane that were ever up
+    pub fn auras(&self, lane: usize) -> Vec<UptimeRow> {
+        let Some(stats) = self.lanes.get(lane) else { return Vec::new() };
+        AURA_NAMES.iter().zip(stats.iter())
+            .filter(|(_, rs)| rs.max > 0.0)
+            .map(|(name, rs)| UptimeRow { name: name.to_string(), stacks: 0, uptime: rs.into() })
+            .collect()
+    }
+}
+
+/// Ignite summary over many iterations; counts are per fight
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IgniteReport {
+    pub ignites: Summary,
+    pub drops: Summary,
+    /// Mean seconds without ignite after a drop
+    pub avg_drop_duration: f64,
+    pub cap_refreshes: Summary,
+    pub late_refreshes: Summary,
+    pub owner_changes: Summary,
+    /// Seconds at full stacks
+    pub five_stack_time: Summary,
+    pub avg_tick: f64,
+    /// Fraction of ticks partially resisted
+    pub resisted_tick_share: f64,
+}
+
+/// Per-iteration ignite counts for the spread, plus totals for the averages
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IgniteStats {
+    ignites: RunningStats,
+    drops: RunningStats,
+    cap_refreshes: RunningStats,
+    late_refreshes: RunningStats,
+    owner_changes: RunningStats,
+    five_stack_time: RunningStats,
+    totals: IgniteMetrics,
+}
+
+impl IgniteStats {
+    pub fn push(&mut self, metrics: &Metrics) {
+        let m = &metrics.ignite;
+        self.ignites.push(m.ignites as f64);
+        self.drops.push(m.drops as f64);
+        self.cap_refreshes.push(m.cap_refreshes as f64);
+        self.late_refreshes.push(m.late_refreshes as f64);
+        self.owner_changes.push(m.owner_changes as f64);
+        self.five_stack_time.push(metrics.uptime.ignite[C::IGNITE_STACK as usize]);
+        self.totals.merge(m);
+    }
+
+    pub fn merge(&mut self, other: &IgniteStats) {
+        self.ignites.merge(&other.ignites);
+        self.drops.merge(&other.drops);
+        self.cap_refreshes.merge(&other.cap_refreshes);
+        self.late_refreshes.merge(&other.late_refreshes);
+        self.owner_changes.merge(&other.owner_changes);
+        self.five_stack_time.merge(&other.five_stack_time);
+        self.totals.merge(&other.totals);
+    }
+
+    pub fn report(&self) -> IgniteReport {
+        let t = &self.totals;
+        IgniteReport {
+            ignites: (&self.ignites).into(),
+            drops: (&self.drops).into(),
+            avg_drop_duration: if t.drops > 0 { t.drop_time / t.drops as f64 } else { 0.0 },
+            cap_refreshes: (&self.cap_refreshes).into(),
+            late_refreshes: (&self.late_refreshes).into(),
+            owner_changes: (&self.owner_changes).into(),
+            five_stack_time: (&self.five_stack_time).into(),
+            avg_tick: if t.ticks > 0 { t.tick_damage / t.ticks as f64 } else { 0.0 },
+            resisted_tick_share: if t.ticks > 0 { t.resisted_ticks as f64 / t.ticks as f64 } else { 0.0 },
+        }
+    }
+}
+
+/// Usage of one cooldown over many iterations
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CooldownReport {
+    pub name: String,
+    /// Fraction of iterations with at least one use
+    pub used_share: f64,
+    pub uses: Summary,
+    /// Over iterations where it was used
+    pub first_use: Summary,
+    pub avg_interval: f64,
+    /// Mean seconds between becoming ready and being used
+    pub avg_delay: f64,
+    pub wasted: Summary,
+    pub ready_at_end: Summary,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+struct CooldownAccumulator {
+    uses: RunningStats,
+    first_use: RunningStats,
+    wasted: RunningStats,
+    ready_at_end: RunningStats,
+    totals: CooldownUsage,
+}
+
+/// Per-lane cooldown usage across iterations
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CooldownStats {
+    lanes: Vec<[CooldownAccumulator; NUM_COOLDOWNS]>,
+}
+
+impl CooldownStats {
+    pub fn push(&mut self, metrics: &Metrics) {
+        if self.lanes.len() < metrics.lanes.len() {
+            self.lanes.resize(metrics.lanes.len(), [CooldownAccumulator::default(); NUM_COOLDOWNS]);
+        }
+        for (acc, lane) in self.lanes.iter_mut().zip(metrics.lanes.iter()) {
+            for (a, u) in acc.iter_mut().zip(lane.cooldowns.iter()) {
+                a.uses.push(u.uses as f64);
+                if u.uses > 0 { a.first_use.push(u.first_use); }
+                a.wasted.push(u.wasted as f64);
+                a.ready_at_end.push(u.ready_at_end);
+                a.totals.merge(u);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &CooldownStats) {
+        if self.lanes.len() < other.lanes.len() {
+            self.lanes.resize(other.lanes.len(), [CooldownAccumulator::default(); NUM_COOLDOWNS]);
+        }
+        for (a, b) in self.lanes.iter_mut().zip(other.lanes.iter()) {
+            for (x, y) in a.iter_mut().zip(b.iter()) {
+                x.uses.merge(&y.uses);
+                x.first_use.merge(&y.first_use);
+                x.wasted.merge(&y.wasted);
+                x.ready_at_end.merge(&y.ready_at_end);
+                x.totals.merge(&y.totals);
+            }
+        }
+    }
+
+    /// Cooldowns of one lane that were used or became ready at least once
+    pub fn report(&self, lane: usize) -> Vec<CooldownReport> {
+        let Some(acc) = self.lanes.get(lane) else { return Vec::new() };
+        COOLDOWN_NAMES.iter().zip(acc.iter())
+            .filter(|(_, a)| a.totals.uses > 0 || a.totals.ready_at_end > 0.0)
+            .map(|(name, a)| {
+                let t = &a.totals;
+                let intervals = t.uses as f64 - a.first_use.count as f64;
+                CooldownReport {
+                    name: name.to_string(),
+                    used_share: if a.uses.count > 0 { a.first_use.count as f64 / a.uses.count as f64 } else { 0.0 },
+                    uses: (&a.uses).into(),
+                    first_use: (&a.first_use).into(),
+                    avg_interval: if intervals > 0.0 { t.interval_sum / intervals } else { 0.0 },
+                    avg_delay: if t.uses > 0 { t.delay_sum / t.uses as f64 } else { 0.0 },
+                    wasted: (&a.wasted).into(),
+                    ready_at_end: (&a.ready_at_end).into(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Time one lane spent in one activity
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ActivityRow {
+    pub activity: String,
+    /// Seconds per fight
+    pub time: Summary,
+    /// Mean share of the fight, 0..1
+    pub share: f64,
+}
+
+/// Per-lane activity time across iterations
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ActivityStats {
+    time: Vec<[RunningStats; NUM_ACTIVITIES]>,
+    share: Vec<[RunningStats; NUM_ACTIVITIES]>,
+}
+
+impl ActivityStats {
+    pub fn push(&mut self, metrics: &Metrics, duration: f64) {
+        let n = metrics.lanes.len();
+        if self.time.len() < n {
+            self.time.resize(n, [RunningStats::default(); NUM_ACTIVITIES]);
+            self.share.resize(n, [RunningStats::default(); NUM_ACTIVITIES]);
+        }
+        let d = duration.max(1e-9);
+        for (i, lane) in metrics.lanes.iter().enumerate() {
+            for (a, t) in lane.activity.iter().enumerate() {
+                self.time[i][a].push(*t);
+                self.share[i][a].push(t / d);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &ActivityStats) {
+        if self.time.len() < other.time.len() {
+            self.time.resize(other.time.len(), [RunningStats::default(); NUM_ACTIVITIES]);
+            self.share.resize(other.share.len(), [RunningStats::default(); NUM_ACTIVITIES]);
+        }
+        for (a, b) in self.time.iter_mut().zip(other.time.iter()) {
+            for (x, y) in a.iter_mut().zip(b.iter()) { x.merge(y); }
+        }
+        for (a, b) in self.share.iter_mut().zip(other.share.iter()) {
+            for (x, y) in a.iter_mut().zip(b.iter()) { x.merge(y); }
+        }
+    }
+
+    pub fn report(&self, lane: usize) -> Vec<ActivityRow> {
+        let (Some(time), Some(share)) = (self.time.get(lane), self.share.get(lane)) else { return Vec::new() };
+        ACTIVITY_NAMES.iter().zip(time.iter().zip(share.iter()))
+            .map(|(name, (t, s))| ActivityRow { activity: name.to_string(), time: t.into(), share: s.mean })
+            .collect()
+    }
+}
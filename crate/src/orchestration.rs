@@ -1,13 +1,16 @@
 //! orchestration.rs — high-level driver and initialization
-use rand::SeedableRng;
-use rand_chacha::ChaCha8Rng;
 use rand_distr::{Normal, Distribution};
 use core::f64;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use serde::{Serialize, Deserialize};
-use crate::constants::{Buff, Constants, ConsumeBuff, RaidBuff, WorldBuff, Racial, BossType, Talent, TalentPoints, TeamTalentPoints};
+use crate::constants::{self as C, Action, Buff, Constants, ConsumeBuff, RaidBuff, WorldBuff, Racial, BossType, Talent, TalentPoints, TeamTalentPoints};
 use crate::state::{State};
 use crate::decisions::Decider;
+use crate::rng::{Mechanic, SimRng};
+use crate::stat_weights::{StatStep, StatWeightAccumulator, StatWeights};
+use crate::metrics::{spell_breakdown, ActivityInterval, ActivityRow, ActivityStats, CooldownReport, CooldownStats, IgniteReport, IgniteStats, Metrics, SpellBreakdown, UptimeRow, UptimeStats};
+use crate::events::{format_log, CombatEvent};
+use crate::stats::{Percentile, QuantileSketch, RunningStats, DEFAULT_PERCENTILES};
 
 
 // ---- Parameters mirrored from Python inputs (trimmed first pass) ----
@@ -52,6 +55,14 @@ pub struct Configuration {
     pub coe: bool,
     pub talents: TeamTalentPoints,
     pub name: Vec<String>,
+    pub rng_seed: u64,
+    pub stat_steps: Vec<StatStep>,
+    pub target_rel_error: Option<f64>,
+    pub percentiles: Vec<f64>,
+    /// Keep logs of the min / median / max DPS iterations of multi-iteration runs
+    pub representative_logs: bool,
+    /// Also log the iteration at this DPS percentile (0..=100)
+    pub log_percentile: Option<f64>,
 }
 
 impl Configuration {
@@ -78,6 +89,12 @@ impl Configuration {
             coe: true,
             talents: TeamTalentPoints::new(0),
             name: Vec::new(),
+            rng_seed: C::DEFAULT_RNG_SEED,
+            stat_steps: StatStep::legacy(),
+            target_rel_error: None,
+            percentiles: DEFAULT_PERCENTILES.to_vec(),
+            representative_logs: false,
+            log_percentile: None,
         }
     }
 }
@@ -103,7 +120,7 @@ pub enum SpellResult {
     Pending,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub enum LogType {
     #[default]
     None,
@@ -115,7 +132,7 @@ pub enum LogType {
     Wait,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct LogEntry {
     pub log_type: LogType,
     pub unit_name: String,
@@ -138,11 +155,20 @@ pub struct DamageAccumulator {
     pub damage: f64,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct PlayerResult {
     pub dmg: u64,
     pub dps: f64,
+    pub std_error: f64,
+    pub std_dev: f64,
+    pub skewness: f64,
     pub ninetieth: f64,
+    pub percentiles: Vec<Percentile>,
+    pub histogram: BTreeMap<u32, u32>,
+    pub spells: Vec<SpellBreakdown>,
+    pub auras: Vec<UptimeRow>,
+    pub cooldowns: Vec<CooldownReport>,
+    pub activity: Vec<ActivityRow>,
     pub name: String,
 }
 
@@ -150,27 +176,40 @@ pub struct PlayerResult {
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct SimulationResult {
     pub iterations: i32,    
+    /// Index the iteration's RNG was derived from
+    pub iteration: u64,
     pub t: f64,
     pub dmg: u64,
     pub dps: f64,
     pub ignite_dmg: u64,
     pub ignite_dps: f64,
     pub players: Vec<PlayerResult>,
+    /// Text rows for the UI log; empty unless rendered with `events::format_log`
     pub log: Vec<LogEntry>,
+    pub events: Vec<CombatEvent>,
     pub damage_log: Vec<f64>,
+    pub metrics: Metrics,
+    pub timeline: Vec<ActivityInterval>,
 }
 
 // Result from multiple runs
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct SimulationsResult {
     pub iterations: i32,
     pub dps: f64,
+    pub dps_std_error: f64,
+    pub converged: bool,
     pub min_dps: f64,
     pub max_dps: f64,
+    pub dps_std_dev: f64,
+    pub dps_skewness: f64,
+    pub percentiles: Vec<Percentile>,
     pub ignite_dps: f64,
     pub players: Vec<PlayerResult>,
-    pub histogram: HashMap<u32, u32>,
+    pub histogram: BTreeMap<u32, u32>,
     pub damage_log: Vec<f64>,
+    pub debuffs: Vec<UptimeRow>,
+    pub ignite: IgniteReport,
     pub dps_sp: f64,
     pub dps_crit: f64,
     pub dps_hit: f64,
@@ -179,18 +218,35 @@ pub struct SimulationsResult {
     pub dps90_crit: f64,
     pub dps90_hit: f64,
     pub dps90_select: f64,
+    pub stat_weights: Option<StatWeights>,
+    /// Logged re-runs of notable iterations, see `Configuration::representative_logs`
+    pub logs: Vec<RepresentativeLog>,
+}
+
+/// Full log of one iteration picked out of a multi-iteration run
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RepresentativeLog {
+    /// "min", "median", "max" or "p<percentile>"
+    pub label: String,
+    pub iteration: u64,
+    pub dps: f64,
+    pub t: f64,
+    pub player_dps: Vec<f64>,
+    pub log: Vec<LogEntry>,
+    pub events: Vec<CombatEvent>,
+    pub timeline: Vec<ActivityInterval>,
 }
 
 // ---- Helpers ----
-fn sample_duration(tim: &Timing, rng: &mut ChaCha8Rng) -> f64 {
+fn sample_duration(tim: &Timing, rng: &mut SimRng) -> f64 {
     let normal = Normal::new(tim.duration_mean, tim.duration_sigma).unwrap();
-    normal.sample(rng).max(tim.duration_mean - tim.duration_sigma)
+    normal.sample(rng.raid(Mechanic::Duration)).max(tim.duration_mean - tim.duration_sigma)
 }
 
-fn first_action_offsets(num_mages: usize, initial_delay: f64, rng: &mut ChaCha8Rng) -> Vec<f64> {
+fn first_action_offsets(num_mages: usize, initial_delay: f64, rng: &mut SimRng) -> Vec<f64> {
     //let mut rng = Pcg64Mcg::seed_from_u64(9);
     let normal = Normal::new(0.0, initial_delay).unwrap();
-    (0..num_mages).map(|_| normal.sample(rng).abs()).collect()
+    (0..num_mages).map(|i| normal.sample(rng.lane(i, Mechanic::Offset)).abs()).collect()
 }
 
 fn apply_buffs(stats: &mut Stats, buffs: &Buffs) {
@@ -258,13 +314,13 @@ fn apply_buffs(stats: &mut Stats, buffs: &Buffs) {
     for hc in &mut stats.hit_chance { *hc += 0.83; }
 }
 
-fn init_state(p: &SimParams, rng: &mut ChaCha8Rng, idx: u64) -> State {
+fn init_state(p: &SimParams, rng: &mut SimRng, log: bool) -> State {
     use crate::constants as C;
 
     let num = p.config.num_mages;
     let mut st = State::new(sample_duration(&p.timing, rng), num);
 
-    st.log_enabled = idx == 0;
+    st.log_enabled = log;
 
     st.meta.cleaner_slots = p.config.udc.clone();
     st.meta.t3_6p_slots = p.config.t3_6p.clone();
@@ -376,172 +432,555 @@ pub fn display_party_stats(st: &State, intellect: Option<&[f64]>) {
 }
 
 
-fn create_rng(seed: u64) -> ChaCha8Rng {
-    ChaCha8Rng::seed_from_u64(seed)
+fn create_rng(seed: u64, num_mages: usize) -> SimRng {
+    SimRng::new(seed, num_mages)
 }
 
+/// Seed of iteration `idx` derived from the master seed (splitmix64 finalizer),
+/// so every iteration is reproducible on its own and neighbouring indices don't correlate.
+pub fn iteration_seed(seed: u64, idx: u64) -> u64 {
+    let mut z = seed ^ idx.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// One iteration; iteration 0 is the one shown in the UI and keeps its log
 pub fn run_single<D: Decider>(params: &SimParams, decider: &mut D, seed: u64, idx: u64) -> SimulationResult {
+    run_iteration(params, decider, seed, idx, idx == 0, true)
+}
 
-    let mut rng = if idx == 0 {
-        // Deterministic for reproducibility of the first run
-        create_rng(seed)
-    } else {
-        // Non-deterministic for parallel workers
-        ChaCha8Rng::from_entropy()
-    };
-
-    let mut k_vec = Vec::with_capacity(params.config.num_mages);
-    for i in 0..params.config.num_mages {
-        if let Some(talent_points) = params.config.talents.get_mage_talents(i) {
-            k_vec.push(Constants::new(&talent_points));
-        } else {
-            // Fallback to default if no talents specified for this mage
-            k_vec.push(Constants::new(&TalentPoints::new()));
+/// Re-run iteration `idx` with the combat log on; same rolls as `run_single`
+pub fn run_single_logged<D: Decider>(params: &SimParams, decider: &mut D, seed: u64, idx: u64) -> SimulationResult {
+    run_iteration(params, decider, seed, idx, true, true)
+}
+
+/// `run_single` without metrics, for runs that only read DPS; same rolls and damage
+pub fn run_single_dps<D: Decider>(params: &SimParams, decider: &mut D, seed: u64, idx: u64) -> SimulationResult {
+    run_iteration(params, decider, seed, idx, false, false)
+}
+
+fn run_iteration<D: Decider>(params: &SimParams, decider: &mut D, seed: u64, idx: u64, log: bool, metrics: bool) -> SimulationResult {
+    let mut sim = Simulation::new(params, seed, idx, log);
+    sim.st.metrics_enabled = metrics;
+    sim.run(decider);
+    sim.finish(params)
+}
+
+/// One iteration as a steppable engine. Cloning forks it: state, constants and RNG
+/// streams are all copied, so a clone continues exactly like the original would.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub st: State,
+    k: Vec<Constants>,
+    rng: SimRng,
+    idx: u64,
+    decisions: usize,
+}
+
+impl Simulation {
+    /// Iteration `idx` of `seed`, before its first decision
+    pub fn new(params: &SimParams, seed: u64, idx: u64, log: bool) -> Self {
+        let mut rng = create_rng(iteration_seed(seed, idx), params.config.num_mages);
+
+        let mut k_vec = Vec::with_capacity(params.config.num_mages);
+        for i in 0..params.config.num_mages {
+            if let Some(talent_points) = params.config.talents.get_mage_talents(i) {
+                k_vec.push(Constants::new(&talent_points));
+            } else {
+                // Fallback to default if no talents specified for this mage
+                k_vec.push(Constants::new(&TalentPoints::new()));
+            }
+        }
+
+        let mut baked_params = params.clone();
+        apply_buffs(&mut baked_params.stats, &params.buffs);
+
+        // Init state
+        let mut st = init_state(&baked_params, &mut rng, log);
+        st.meta.cast_time = k_vec.iter().map(|k| k.cast_time).collect();
+        st.meta.spell_travel = k_vec.iter().map(|k| k.spell_travel).collect();
+
+        if st.log_enabled {
+        // show effective stats & ready buffs
+            display_party_stats(&st, Some(&baked_params.stats.intellect));
         }
+
+        Self { st, k: k_vec, rng, idx, decisions: 0 }
+    }
+
+    /// Stop collecting metrics; rollouts and drivers that only compare damage use this
+    pub fn without_metrics(mut self) -> Self {
+        self.st.metrics_enabled = false;
+        self
+    }
+
+    /// Decisions taken so far
+    pub fn decisions(&self) -> usize { self.decisions }
+
+    pub fn time(&self) -> f64 { self.st.global.running_time }
+
+    pub fn in_progress(&self) -> bool { self.st.in_progress() }
+
+    /// Damage dealt so far, ignite included
+    pub fn damage(&self) -> f64 { self.st.totals.total_damage + self.st.totals.ignite_damage }
+
+    /// Lane that decides next
+    pub fn next_lane(&self) -> Option<usize> { self.st.next_cast_lane() }
+
+    /// Replace the RNG streams so that forks given different seeds see different rolls
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = create_rng(seed, self.st.lanes.len());
     }
 
-    let mut baked_params = params.clone();
-    apply_buffs(&mut baked_params.stats, &params.buffs);
+    /// Let `decider` take one decision, then play the fight up to the next one
+    pub fn step<D: Decider>(&mut self, decider: &mut D) {
+        self.st.watch_enabled |= decider.watches();
+        if let Some((lane, action, delay_sigma)) = decider.next_action(&self.st) {
+            // sample continuing delay, unless the decider fixes it
+            let continuing_delay: f64 = match decider.fixed_delay(&self.st, lane) {
+                Some(delay) => delay,
+                None => self.reaction_delay(lane, delay_sigma),
+            };
+            self.start(lane, action, continuing_delay);
+        }
+        self.advance();
+    }
 
-    // Init state
-    let mut st = init_state(&baked_params, &mut rng, idx);
+    /// Like `step`, but `lane` starts `action` after a reaction rolled from `delay_sigma`
+    pub fn step_action(&mut self, lane: usize, action: Action, delay_sigma: f64) {
+        let continuing_delay = self.reaction_delay(lane, delay_sigma);
+        self.start(lane, action, continuing_delay);
+        self.advance();
+    }
 
-    if st.log_enabled {
-    // show effective stats & ready buffs
-        display_party_stats(&st, Some(&baked_params.stats.intellect));
+    /// Step until the clock reaches `t` (or the fight ends)
+    pub fn run_to_time<D: Decider>(&mut self, decider: &mut D, t: f64) {
+        while self.in_progress() && self.time() < t {
+            self.step(decider);
+        }
     }
 
-    while st.in_progress() {
-        
-        if let Some((lane, action, delay_sigma)) = decider.next_action(&st) {
-            // sample continuing delay
-            let normal = Normal::new(0.0, delay_sigma).unwrap();
-            let continuing_delay: f64 = normal.sample(&mut rng).abs();
-            
-            st.start_action(lane, action, continuing_delay, &k_vec);
+    /// Step until `n` decisions have been taken (or the fight ends)
+    pub fn run_to_decision<D: Decider>(&mut self, decider: &mut D, n: usize) {
+        while self.in_progress() && self.decisions < n {
+            self.step(decider);
         }
+    }
 
-        // step one event
-        while !st.decision_gate() && st.in_progress() {
-            st.step_one(&k_vec, &mut rng);
+    /// Play the rest of the fight
+    pub fn run<D: Decider>(&mut self, decider: &mut D) {
+        while self.in_progress() {
+            self.step(decider);
         }
     }
 
-    // Aggregate DPS
-    let dur = st.global.duration.max(1e-9);
-    let mut players = Vec::<PlayerResult>::new();
-    for i in 0..st.lanes.len() {
-        let dmg = st.lanes[i].damage;
-        let total_dmg = dmg + st.totals.ignite_damage / (st.lanes.len() as f64);
-        //log::debug!("{:3} player {} amount {:4.}", idx, i, (st.lanes.len() as f64) * dmg/dur);
-        players.push(PlayerResult {
-            name: params.config.name[i].clone(),
-            dmg: dmg as u64,
-            dps: total_dmg /dur,
-            ninetieth: 0.0,
-        });
+    fn reaction_delay(&mut self, lane: usize, delay_sigma: f64) -> f64 {
+        Normal::new(0.0, delay_sigma).unwrap().sample(self.rng.lane(lane, Mechanic::Reaction)).abs()
+    }
+
+    fn start(&mut self, lane: usize, action: Action, continuing_delay: f64) {
+        self.st.start_action(lane, action, continuing_delay, &self.k);
+        self.decisions += 1;
     }
 
-    // build damage over time
-    const DELTA_T: f64 = 0.25;
-    let num_intervals = if !st.log_enabled { ((params.timing.duration_mean - params.timing.duration_sigma) / DELTA_T).ceil() as usize } else { 0 };
-    let mut total_damage = Vec::with_capacity(num_intervals);
-    if !st.log_enabled {
-        let mut cumulative = 0.0;
-        let mut log_index = 0;
-        total_damage.push(0.0);  // starting at t= 0.0
-        for tdx in 0..num_intervals {
-            let time_point = (tdx + 1) as f64 * DELTA_T;
-            // Add all damage that occurred in this interval
-            while log_index < st.damage_log.len() && st.damage_log[log_index].time <= time_point {
-                cumulative += st.damage_log[log_index].damage;
-                log_index += 1;
+    // step events up to the next decision
+    fn advance(&mut self) {
+        while !self.st.decision_gate() && self.st.in_progress() {
+            self.st.step_one(&self.k, &mut self.rng);
+        }
+    }
+
+    /// Close the metrics and summarise the iteration
+    pub fn finish(self, params: &SimParams) -> SimulationResult {
+        let mut st = self.st;
+        st.finish_metrics();
+
+        // Aggregate DPS
+        let dur = st.global.duration.max(1e-9);
+        let mut players = Vec::<PlayerResult>::new();
+        for i in 0..st.lanes.len() {
+            let dmg = st.lanes[i].damage;
+            let total_dmg = dmg + st.totals.ignite_damage / (st.lanes.len() as f64);
+            //log::debug!("{:3} player {} amount {:4.}", idx, i, (st.lanes.len() as f64) * dmg/dur);
+            players.push(PlayerResult {
+                name: params.config.name[i].clone(),
+                dmg: dmg as u64,
+                dps: total_dmg /dur,
+                // tables for the single logged run; aggregated runs build them in `finish`
+                spells: if st.log_enabled { spell_breakdown(&st.metrics.lanes[i], 1, dur) } else { Vec::new() },
+                ..Default::default()
+            });
+        }
+
+        // build damage over time
+        const DELTA_T: f64 = 0.25;
+        let num_intervals = if !st.log_enabled { ((params.timing.duration_mean - params.timing.duration_sigma) / DELTA_T).ceil() as usize } else { 0 };
+        let mut total_damage = Vec::with_capacity(num_intervals);
+        if !st.log_enabled {
+            let mut cumulative = 0.0;
+            let mut log_index = 0;
+            total_damage.push(0.0);  // starting at t= 0.0
+            for tdx in 0..num_intervals {
+                let time_point = (tdx + 1) as f64 * DELTA_T;
+                // Add all damage that occurred in this interval
+                while log_index < st.damage_log.len() && st.damage_log[log_index].time <= time_point {
+                    cumulative += st.damage_log[log_index].damage;
+                    log_index += 1;
+                }
+                total_damage.push(cumulative / time_point);
             }
-            total_damage.push(cumulative / time_point);
+        }
+
+        SimulationResult {
+            iterations: 1,
+            iteration: self.idx,
+            t: dur,
+            dmg: (st.totals.total_damage + st.totals.ignite_damage) as u64,
+            dps: (st.totals.total_damage + st.totals.ignite_damage) /dur,
+            ignite_dmg: st.totals.ignite_damage as u64,
+            ignite_dps: st.totals.ignite_damage /dur,
+            players,
+            log: Vec::new(),
+            events: st.events,
+            damage_log: total_damage,
+            metrics: st.metrics,
+            timeline: st.timeline,
         }
     }
+}
 
-    let result = SimulationResult {
-        iterations: 1,
-        t: dur,
-        dmg: (st.totals.total_damage + st.totals.ignite_damage) as u64,
-        dps: (st.totals.total_damage + st.totals.ignite_damage) /dur,
-        ignite_dmg: st.totals.ignite_damage as u64,
-        ignite_dps: st.totals.ignite_damage /dur,
-        players: players.clone(),
-        log: st.log.clone(),
-        damage_log: total_damage.clone(),
-    };
+const BIN_SIZE: f64 = 50.0;
+const PLAYER_BIN_SIZE: f64 = 10.0;
 
-    result.clone()
+fn add_to_histogram(histogram: &mut BTreeMap<u32, u32>, dps: f64, bin_size: f64) {
+    let bin = ((dps / bin_size).floor() * bin_size) as u32;
+    *histogram.entry(bin).or_insert(0) += 1;
 }
 
-pub fn run_many_with<D, F>(params: &SimParams, make_decider: F, iterations: i32, seed: u64) -> SimulationsResult
-where
-    D: Decider,
-    F: Fn() -> D,
-{
-    let mut result: SimulationsResult = SimulationsResult { iterations, ..Default::default() };
-    const BIN_SIZE: f64 = 50.0;
-    let mut dps_values = vec![Vec::with_capacity(iterations as usize); params.config.num_mages];
+fn percentiles_of(sketch: &mut QuantileSketch, ps: &[f64]) -> Vec<Percentile> {
+    ps.iter().map(|&p| Percentile { p, dps: sketch.percentile(p) }).collect()
+}
 
-    for idx in 1..=iterations {
-        // Fresh decider for each iteration
-        let mut decider = make_decider();
+/// Running totals over iterations; `finish` turns them into a `SimulationsResult`.
+/// Chunks run separately (e.g. one per web worker) combine with `merge`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimAccumulator {
+    percentiles: Vec<f64>,
+    /// (iteration, raid DPS) of every iteration; only kept for representative logs
+    keep_samples: bool,
+    log_percentile: Option<f64>,
+    samples: Vec<(u64, f64)>,
+    iterations: i32,
+    dps: RunningStats,
+    ignite_dps: RunningStats,
+    min_dps: f64,
+    max_dps: f64,
+    players: Vec<PlayerResult>,
+    player_dps: Vec<RunningStats>,
+    raid_dps_sketch: QuantileSketch,
+    dps_sketches: Vec<QuantileSketch>,
+    histogram: BTreeMap<u32, u32>,
+    damage_log: Vec<f64>,
+    fight_time: f64,
+    metrics: Metrics,
+    uptime: UptimeStats,
+    ignite: IgniteStats,
+    cooldowns: CooldownStats,
+    activity: ActivityStats,
+    /// Paired against the main runs when the config asks for stat weights
+    stat_weights: Option<StatWeightAccumulator>,
+}
 
-        // If you want a per-iter seed, keep passing i as u64 like before
-        let sim_result = run_single(params, &mut decider, seed, idx as u64);
+impl SimAccumulator {
+    /// Reports `config.percentiles` besides the 90th and keeps what
+    /// `representative_logs` needs. Stat weights, if asked for, always include the
+    /// legacy SP / crit / hit steps, since the JS side reads their fields.
+    pub fn new(config: &Configuration) -> Self {
+        let stat_weights = (!config.target.is_empty() && !config.vary.is_empty() && config.do_stat_weights)
+            .then(|| StatWeightAccumulator::new(&config.target, &StatStep::with_legacy(&config.stat_steps)));
+        Self {
+            percentiles: config.percentiles.clone(),
+            keep_samples: config.representative_logs,
+            log_percentile: config.log_percentile,
+            stat_weights,
+            ..Default::default()
+        }
+    }
+
+    /// Configs the stat-weight steps run with; empty without stat weights
+    fn perturbed(&self, params: &SimParams) -> Vec<SimParams> {
+        self.stat_weights.as_ref().map_or_else(Vec::new, |w| w.perturbed(params))
+    }
 
-        for jdx in 0..params.config.num_mages {
-            dps_values[jdx].push(sim_result.players[jdx].dps);
+    /// Add iteration `idx` and pair the stat-weight steps with it
+    fn add_paired<D, F>(&mut self, perturbed: &[SimParams], make_decider: F, seed: u64, idx: u64, sim_result: &SimulationResult)
+    where
+        D: Decider,
+        F: Fn() -> D,
+    {
+        if let Some(weights) = self.stat_weights.as_mut() {
+            weights.add(perturbed, make_decider, seed, idx, sim_result);
         }
+        self.add(sim_result);
+    }
 
-        result.dps += sim_result.dps;
-        result.ignite_dps += sim_result.ignite_dps as f64;
+    pub fn iterations(&self) -> i32 { self.iterations }
 
-        if idx == 1 || sim_result.dps < result.min_dps {
-            result.min_dps = sim_result.dps;
+    /// Relative standard error of the raid DPS mean
+    pub fn relative_error(&self) -> f64 {
+        if self.dps.mean > 0.0 { self.dps.std_error() / self.dps.mean } else { f64::INFINITY }
+    }
+
+    pub fn add(&mut self, sim_result: &SimulationResult) {
+        self.iterations += 1;
+        let first = self.iterations == 1;
+
+        self.dps.push(sim_result.dps);
+        self.ignite_dps.push(sim_result.ignite_dps);
+        if self.keep_samples {
+            self.samples.push((sim_result.iteration, sim_result.dps));
+        }
+
+        if first || sim_result.dps < self.min_dps {
+            self.min_dps = sim_result.dps;
         }
-        if idx == 1 || sim_result.dps > result.max_dps {
-            result.max_dps = sim_result.dps;
+        if first || sim_result.dps > self.max_dps {
+            self.max_dps = sim_result.dps;
         }
 
-        let bin = ((sim_result.dps / BIN_SIZE).floor() * BIN_SIZE) as u32;
-        if let Some(num) = result.histogram.get_mut(&bin) {
-            *num += 1;
+        add_to_histogram(&mut self.histogram, sim_result.dps, BIN_SIZE);
+        self.raid_dps_sketch.push(sim_result.dps);
+        self.fight_time += sim_result.t;
+        self.metrics.merge(&sim_result.metrics);
+        self.uptime.push(&sim_result.metrics.uptime, sim_result.t);
+        self.ignite.push(&sim_result.metrics);
+        self.cooldowns.push(&sim_result.metrics);
+        self.activity.push(&sim_result.metrics, sim_result.t);
+
+        if first {
+            self.players.clone_from(&sim_result.players);
+            self.player_dps = vec![RunningStats::default(); sim_result.players.len()];
+            self.dps_sketches = vec![QuantileSketch::default(); sim_result.players.len()];
+            self.damage_log = sim_result.damage_log.clone();
         } else {
-            result.histogram.insert(bin, 1);
+            for (x, y) in self.damage_log.iter_mut().zip(sim_result.damage_log.iter()) {
+                *x += y;
+            }
         }
+        for (jdx, pr) in sim_result.players.iter().enumerate() {
+            self.player_dps[jdx].push(pr.dps);
+            self.dps_sketches[jdx].push(pr.dps);
+            add_to_histogram(&mut self.players[jdx].histogram, pr.dps, PLAYER_BIN_SIZE);
+        }
+    }
 
-        if idx == 1 {
-            result.players.clone_from(&sim_result.players);
-        } else {
-            for (jdx, pr) in sim_result.players.iter().enumerate() {
-                result.players[jdx].dps += pr.dps;
+    /// Fold in a chunk covering other iterations of the same config
+    pub fn merge(&mut self, other: &SimAccumulator) {
+        if other.iterations == 0 { return; }
+        if self.iterations == 0 {
+            let percentiles = std::mem::take(&mut self.percentiles);
+            *self = other.clone();
+            if !percentiles.is_empty() { self.percentiles = percentiles; }
+            return;
+        }
+
+        self.samples.extend_from_slice(&other.samples);
+
+        self.iterations += other.iterations;
+        self.dps.merge(&other.dps);
+        self.ignite_dps.merge(&other.ignite_dps);
+        self.min_dps = self.min_dps.min(other.min_dps);
+        self.max_dps = self.max_dps.max(other.max_dps);
+        self.raid_dps_sketch.merge(&other.raid_dps_sketch);
+        self.fight_time += other.fight_time;
+        self.metrics.merge(&other.metrics);
+        self.uptime.merge(&other.uptime);
+        self.ignite.merge(&other.ignite);
+        self.cooldowns.merge(&other.cooldowns);
+        self.activity.merge(&other.activity);
+        if let (Some(weights), Some(other)) = (self.stat_weights.as_mut(), other.stat_weights.as_ref()) {
+            weights.merge(other);
+        }
+        for (bin, count) in &other.histogram {
+            *self.histogram.entry(*bin).or_insert(0) += count;
+        }
+
+        for (jdx, player) in self.players.iter_mut().enumerate() {
+            self.player_dps[jdx].merge(&other.player_dps[jdx]);
+            self.dps_sketches[jdx].merge(&other.dps_sketches[jdx]);
+            for (bin, count) in &other.players[jdx].histogram {
+                *player.histogram.entry(*bin).or_insert(0) += count;
             }
         }
-        if idx == 1 {
-            result.damage_log = sim_result.damage_log.clone();
-        } else {
-            for (x, y) in result.damage_log.iter_mut().zip(sim_result.damage_log.iter()) {
-                *x += y;
+
+        if other.damage_log.len() > self.damage_log.len() {
+            self.damage_log.resize(other.damage_log.len(), 0.0);
+        }
+        for (x, y) in self.damage_log.iter_mut().zip(other.damage_log.iter()) {
+            *x += y;
+        }
+    }
+
+    /// (label, iteration, dps) of the min, median, max and `log_percentile` iterations
+    pub fn representatives(&self) -> Vec<(String, u64, f64)> {
+        if self.samples.is_empty() { return Vec::new(); }
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        let at = |p: f64| sorted[((p / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f64).round() as usize];
+
+        let mut picks = vec![("min".to_string(), at(0.0)), ("median".to_string(), at(50.0)), ("max".to_string(), at(100.0))];
+        if let Some(p) = self.log_percentile {
+            picks.push((format!("p{}", p), at(p)));
+        }
+        picks.into_iter().map(|(label, (idx, dps))| (label, idx, dps)).collect()
+    }
+
+    pub fn finish(mut self) -> SimulationsResult {
+        let iterations = self.iterations;
+        let mut players = std::mem::take(&mut self.players);
+        for (jdx, player) in players.iter_mut().enumerate() {
+            player.dps = self.player_dps[jdx].mean;
+            player.std_error = self.player_dps[jdx].std_error();
+            player.std_dev = self.player_dps[jdx].std_dev();
+            player.skewness = self.player_dps[jdx].skewness();
+            player.ninetieth = self.dps_sketches[jdx].percentile(90.0);
+            player.percentiles = percentiles_of(&mut self.dps_sketches[jdx], &self.percentiles);
+            if let Some(lane) = self.metrics.lanes.get(jdx) {
+                player.spells = spell_breakdown(lane, iterations, self.fight_time);
             }
+            player.auras = self.uptime.auras(jdx);
+            player.cooldowns = self.cooldowns.report(jdx);
+            player.activity = self.activity.report(jdx);
         }
+        for d in self.damage_log.iter_mut() { *d /= iterations.max(1) as f64; }
+        let stat_weights = self.stat_weights.take().filter(|w| w.iterations() > 0).map(StatWeightAccumulator::finish);
+
+        let mut result = SimulationsResult {
+            iterations,
+            dps: self.dps.mean,
+            dps_std_error: self.dps.std_error(),
+            min_dps: self.min_dps,
+            max_dps: self.max_dps,
+            dps_std_dev: self.dps.std_dev(),
+            dps_skewness: self.dps.skewness(),
+            percentiles: percentiles_of(&mut self.raid_dps_sketch, &self.percentiles),
+            ignite_dps: self.ignite_dps.mean,
+            players,
+            histogram: self.histogram,
+            damage_log: self.damage_log,
+            debuffs: self.uptime.debuffs(),
+            ignite: self.ignite.report(),
+            ..Default::default()
+        };
+        if let Some(weights) = stat_weights {
+            weights.fill_legacy(&mut result);
+            result.stat_weights = Some(weights);
+        }
+        result
     }
+}
 
-    // Calculate 90th percentile
-    for jdx in 0..params.config.num_mages {
-        dps_values[jdx].sort_by(|a, b| a.partial_cmp(b).unwrap());
-        let percentile_90_index = ((iterations as f64) * 0.9).ceil() as usize - 1;
-        result.players[jdx].ninetieth = dps_values[jdx][percentile_90_index.min(iterations as usize - 1)];
+pub fn run_many_with<D, F>(params: &SimParams, make_decider: F, iterations: i32, seed: u64) -> SimulationsResult
+where
+    D: Decider,
+    F: Fn() -> D,
+{
+    let acc = run_chunk_with(params, &make_decider, seed, 1, iterations);
+    let logs = representative_logs(params, &make_decider, seed, &acc);
+    let mut result = acc.finish();
+    result.logs = logs;
+    result
+}
+
+/// Re-run the iterations picked by `acc.representatives()` with logging on. While
+/// running, only an (iteration, dps) pair per iteration is kept instead of its log;
+/// that list still grows with the iteration count, since the median and
+/// `log_percentile` picks need every sample.
+pub fn representative_logs<D, F>(params: &SimParams, make_decider: F, seed: u64, acc: &SimAccumulator) -> Vec<RepresentativeLog>
+where
+    D: Decider,
+    F: Fn() -> D,
+{
+    acc.representatives().into_iter().map(|(label, idx, dps)| {
+        let result = run_single_logged(params, &mut make_decider(), seed, idx);
+        RepresentativeLog {
+            label,
+            iteration: idx,
+            dps,
+            t: result.t,
+            player_dps: result.players.iter().map(|p| p.dps).collect(),
+            log: format_log(&result.events, &params.config.name),
+            events: result.events,
+            timeline: result.timeline,
+        }
+    }).collect()
+}
+
+/// Run iterations `first_iteration..first_iteration + iterations`. Chunks covering
+/// `1..=n` run the same fights as `run_many_with(.., n, ..)`; their merge is a
+/// statistically equivalent result, since the merged means, moments and percentile
+/// sketches depend on the order the samples were combined in.
+pub fn run_chunk_with<D, F>(params: &SimParams, make_decider: F, seed: u64, first_iteration: u64, iterations: i32) -> SimAccumulator
+where
+    D: Decider,
+    F: Fn() -> D,
+{
+    let mut acc = SimAccumulator::new(&params.config);
+    let perturbed = acc.perturbed(params);
+
+    for idx in first_iteration..first_iteration + iterations.max(0) as u64 {
+        // Fresh decider for each iteration
+        let mut decider = make_decider();
+
+        // Each iteration derives its own RNG from (seed, idx)
+        let sim_result = run_single(params, &mut decider, seed, idx);
+        acc.add_paired(&perturbed, &make_decider, seed, idx, &sim_result);
     }
 
-    result.dps /= iterations as f64;
-    result.ignite_dps /= iterations as f64;
-    for jdx in 0..result.players.len() {
-        result.players[jdx].dps /= iterations as f64;
+    acc
+}
+
+/// Stop rule for `run_until_converged`
+#[derive(Debug, Clone, Copy)]
+pub struct Convergence {
+    /// Stop once std_error / mean of raid DPS drops to this
+    pub target_rel_error: f64,
+    pub min_iterations: i32,
+    pub max_iterations: i32,
+}
+
+impl Convergence {
+    pub fn new(target_rel_error: f64, max_iterations: i32) -> Self {
+        Self { target_rel_error, min_iterations: 100.min(max_iterations), max_iterations }
+    }
+}
+
+/// Like `run_many_with`, but stops as soon as the raid DPS is known to the requested
+/// relative error. `iterations` of the result is the number actually run.
+pub fn run_until_converged<D, F>(params: &SimParams, make_decider: F, convergence: Convergence, seed: u64) -> SimulationsResult
+where
+    D: Decider,
+    F: Fn() -> D,
+{
+    let mut acc = SimAccumulator::new(&params.config);
+    let perturbed = acc.perturbed(params);
+    let mut converged = false;
+
+    for idx in 1..=convergence.max_iterations {
+        let mut decider = make_decider();
+        let sim_result = run_single(params, &mut decider, seed, idx as u64);
+        acc.add_paired(&perturbed, &make_decider, seed, idx as u64, &sim_result);
+
+        if idx >= convergence.min_iterations && acc.relative_error() <= convergence.target_rel_error {
+            converged = true;
+            break;
+        }
     }
-    for d in result.damage_log.iter_mut() { *d /= iterations as f64; }
 
-    result  
+    let logs = representative_logs(params, &make_decider, seed, &acc);
+    let mut result = acc.finish();
+    result.converged = converged;
+    result.logs = logs;
+    result
 }
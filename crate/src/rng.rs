@@ -0,0 +1,75 @@
+//! rng.rs — independent random streams per lane and per mechanic
+//!
+//! Every mechanic of every lane draws from its own ChaCha stream, all keyed by the
+//! iteration seed. Two configs that differ slightly (e.g. +1% hit) still see the same
+//! n-th damage roll, crit roll, resist roll ... on each lane, which keeps paired
+//! comparisons (stat weights, A/B runs) low-variance.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mechanic {
+    // fight setup
+    Duration = 0,
+    Offset = 1,
+    // decisions
+    Reaction = 2,
+    // spell landing
+    Hit = 3,
+    DamageRoll = 4,
+    PartialResist = 5,
+    Crit = 6,
+    ScorchProc = 7,
+    WintersChill = 8,
+    T2Proc = 9,
+    T3Proc = 10,
+    // boss side
+    Nightfall = 11,
+    IgniteResist = 12,
+}
+
+pub const NUM_MECHANICS: usize = 13;
+
+/// Lazily created streams: slot 0 is the raid/boss, slot `lane + 1` each mage.
+#[derive(Debug, Clone)]
+pub struct SimRng {
+    seed: u64,
+    streams: Vec<Option<ChaCha8Rng>>,
+}
+
+impl SimRng {
+    pub fn new(seed: u64, num_lanes: usize) -> Self {
+        Self { seed, streams: vec![None; (num_lanes + 1) * NUM_MECHANICS] }
+    }
+
+    fn slot(&mut self, slot: usize, mechanic: Mechanic) -> &mut ChaCha8Rng {
+        let id = slot * NUM_MECHANICS + mechanic as usize;
+        let seed = self.seed;
+        self.streams[id].get_or_insert_with(|| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(id as u64);
+            rng
+        })
+    }
+
+    /// Stream of a mechanic owned by one lane
+    pub fn lane(&mut self, lane: usize, mechanic: Mechanic) -> &mut ChaCha8Rng {
+        self.slot(lane + 1, mechanic)
+    }
+
+    /// Stream of a mechanic not tied to a lane (fight length, boss procs, ignite)
+    pub fn raid(&mut self, mechanic: Mechanic) -> &mut ChaCha8Rng {
+        self.slot(0, mechanic)
+    }
+
+    /// Uniform draw in [0, 1) from a lane stream
+    pub fn roll(&mut self, lane: usize, mechanic: Mechanic) -> f64 {
+        self.lane(lane, mechanic).r#gen::<f64>()
+    }
+
+    /// Uniform draw in [0, 1) from a raid stream
+    pub fn roll_raid(&mut self, mechanic: Mechanic) -> f64 {
+        self.raid(mechanic).r#gen::<f64>()
+    }
+}
@@ -0,0 +1,340 @@
+//! stat_weights.rs — stat weights from paired baseline / perturbed runs
+//!
+//! Every iteration runs the baseline and each perturbed config with the same
+//! iteration seed. With per-mechanic RNG streams (see `rng.rs`) both runs see the
+//! same rolls wherever possible, so the per-iteration DPS difference has far less
+//! variance than the difference of two independent means.
+
+use serde::{Serialize, Deserialize};
+use crate::decisions::Decider;
+use crate::orchestration::{run_single_dps, SimParams, SimulationResult, SimulationsResult, Stats};
+use crate::stats::{Estimate, QuantileSketch, RunningStats};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Stat {
+    SpellPower,
+    FirePower,
+    FrostPower,
+    Crit,
+    Hit,
+    Intellect,
+}
+
+impl Stat {
+    /// Add `step` of this stat to the raw (unbuffed) stats of `mages`.
+    /// Crit and hit are fractions (0.01 = 1%).
+    pub fn apply(self, stats: &mut Stats, mages: &[usize], step: f64) {
+        for &i in mages {
+            match self {
+                Stat::SpellPower => {
+                    stats.fire_power[i] += step;
+                    stats.frost_power[i] += step;
+                }
+                Stat::FirePower => stats.fire_power[i] += step,
+                Stat::FrostPower => stats.frost_power[i] += step,
+                Stat::Crit => stats.crit_chance[i] += step,
+                Stat::Hit => stats.hit_chance[i] += step,
+                Stat::Intellect => stats.intellect[i] += step,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatStep {
+    pub stat: Stat,
+    pub step: f64,
+}
+
+impl StatStep {
+    pub fn new(stat: Stat, step: f64) -> Self { Self { stat, step } }
+
+    /// The three perturbations the UI has always shown: +15 SP, +1.5% crit, -1.5% hit
+    pub fn legacy() -> Vec<StatStep> {
+        vec![
+            StatStep::new(Stat::SpellPower, 15.0),
+            StatStep::new(Stat::Crit, 0.015),
+            StatStep::new(Stat::Hit, -0.015),
+        ]
+    }
+
+    /// Every supported stat; hit steps down since most raid mages sit at the cap
+    pub fn all() -> Vec<StatStep> {
+        vec![
+            StatStep::new(Stat::SpellPower, 15.0),
+            StatStep::new(Stat::FirePower, 15.0),
+            StatStep::new(Stat::FrostPower, 15.0),
+            StatStep::new(Stat::Crit, 0.015),
+            StatStep::new(Stat::Hit, -0.015),
+            StatStep::new(Stat::Intellect, 15.0),
+        ]
+    }
+
+    /// A zero (or non-finite) step measures nothing and has no per-unit weight
+    pub fn is_valid(&self) -> bool {
+        self.step != 0.0 && self.step.is_finite()
+    }
+
+    /// The valid entries of `steps`, plus any of the three legacy steps whose stat is missing
+    pub fn with_legacy(steps: &[StatStep]) -> Vec<StatStep> {
+        let mut out: Vec<StatStep> = steps.iter().copied().filter(StatStep::is_valid).collect();
+        for s in StatStep::legacy() {
+            if !out.iter().any(|o| o.stat == s.stat) { out.push(s); }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatWeight {
+    pub stat: Stat,
+    pub step: f64,
+    /// Mean target DPS with the step applied
+    pub dps: f64,
+    pub dps90: f64,
+    /// Paired mean DPS change and its standard error
+    pub delta: f64,
+    pub std_error: f64,
+    /// DPS change per unit of stat (per SP, per 1.0 crit, ...)
+    pub per_unit: f64,
+    pub per_unit_error: f64,
+    /// SP worth one unit of this stat; 0 when no SpellPower step was run
+    pub sp_equivalent: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatWeights {
+    pub iterations: i32,
+    /// Baseline mean / 90th percentile target DPS
+    pub dps: f64,
+    pub dps90: f64,
+    pub weights: Vec<StatWeight>,
+}
+
+/// Mean DPS of the target players in one run
+pub fn target_dps(result: &SimulationResult, target: &[usize]) -> f64 {
+    if target.is_empty() { return result.dps; }
+    target.iter().map(|&i| result.players[i].dps).sum::<f64>() / target.len() as f64
+}
+
+/// Perturb `params.config.vary`, measure `params.config.target`. Invalid steps are
+/// skipped. Runs its own baseline, so a call costs `(steps + 1) * iterations` runs;
+/// a main run pairs against its own iterations instead, see `StatWeightAccumulator`.
+pub fn run_stat_weights<D, F>(params: &SimParams, make_decider: F, iterations: i32, seed: u64, steps: &[StatStep]) -> StatWeights
+where
+    D: Decider,
+    F: Fn() -> D,
+{
+    let mut acc = StatWeightAccumulator::new(&params.config.target, steps);
+    let perturbed = acc.perturbed(params);
+    for idx in 1..=iterations.max(0) as u64 {
+        let base = run_single_dps(params, &mut make_decider(), seed, idx);
+        acc.add(&perturbed, &make_decider, seed, idx, &base);
+    }
+    acc.finish()
+}
+
+/// Paired stat-weight totals, fed one iteration at a time with that iteration's
+/// baseline run. Chunks of a split run combine with `merge`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatWeightAccumulator {
+    target: Vec<usize>,
+    steps: Vec<StatStep>,
+    iterations: i32,
+    base_dps: RunningStats,
+    base_sketch: QuantileSketch,
+    step_dps: Vec<RunningStats>,
+    step_sketches: Vec<QuantileSketch>,
+    deltas: Vec<RunningStats>,
+}
+
+impl StatWeightAccumulator {
+    /// Measures the mean DPS of `target`; invalid steps are skipped
+    pub fn new(target: &[usize], steps: &[StatStep]) -> Self {
+        let steps: Vec<StatStep> = steps.iter().copied().filter(StatStep::is_valid).collect();
+        Self {
+            step_dps: vec![RunningStats::default(); steps.len()],
+            step_sketches: vec![QuantileSketch::default(); steps.len()],
+            deltas: vec![RunningStats::default(); steps.len()],
+            target: target.to_vec(),
+            steps,
+            ..Default::default()
+        }
+    }
+
+    pub fn iterations(&self) -> i32 { self.iterations }
+
+    /// `params` with each step applied to `params.config.vary`, in step order
+    pub fn perturbed(&self, params: &SimParams) -> Vec<SimParams> {
+        self.steps.iter().map(|s| {
+            let mut p = params.clone();
+            s.stat.apply(&mut p.stats, &params.config.vary, s.step);
+            p
+        }).collect()
+    }
+
+    /// Run the `perturbed` configs of iteration `idx` and pair them with `base`,
+    /// the baseline run of the same iteration
+    pub fn add<D, F>(&mut self, perturbed: &[SimParams], make_decider: F, seed: u64, idx: u64, base: &SimulationResult)
+    where
+        D: Decider,
+        F: Fn() -> D,
+    {
+        self.iterations += 1;
+        let base = target_dps(base, &self.target);
+        self.base_dps.push(base);
+        self.base_sketch.push(base);
+        for (sdx, p) in perturbed.iter().enumerate() {
+            let dps = target_dps(&run_single_dps(p, &mut make_decider(), seed, idx), &self.target);
+            self.step_dps[sdx].push(dps);
+            self.step_sketches[sdx].push(dps);
+            self.deltas[sdx].push(dps - base);
+        }
+    }
+
+    /// Fold in a chunk covering other iterations with the same steps
+    pub fn merge(&mut self, other: &StatWeightAccumulator) {
+        self.iterations += other.iterations;
+        self.base_dps.merge(&other.base_dps);
+        self.base_sketch.merge(&other.base_sketch);
+        for sdx in 0..self.steps.len().min(other.steps.len()) {
+            self.step_dps[sdx].merge(&other.step_dps[sdx]);
+            self.step_sketches[sdx].merge(&other.step_sketches[sdx]);
+            self.deltas[sdx].merge(&other.deltas[sdx]);
+        }
+    }
+
+    pub fn finish(mut self) -> StatWeights {
+        let mut weights: Vec<StatWeight> = self.steps.iter().zip(self.deltas.iter()).zip(self.step_dps.iter().zip(self.step_sketches.iter_mut()))
+            .map(|((s, d), (dps, sketch))| StatWeight {
+                stat: s.stat,
+                step: s.step,
+                dps: dps.mean,
+                dps90: sketch.percentile(90.0),
+                delta: d.mean,
+                std_error: d.std_error(),
+                per_unit: d.mean / s.step,
+                per_unit_error: d.std_error() / s.step.abs(),
+                sp_equivalent: 0.0,
+            })
+            .collect();
+
+        let sp = weights.iter().find(|w| w.stat == Stat::SpellPower).map(|w| w.per_unit).unwrap_or(0.0);
+        if sp != 0.0 {
+            for w in weights.iter_mut() { w.sp_equivalent = w.per_unit / sp; }
+        }
+
+        StatWeights {
+            iterations: self.iterations,
+            dps: self.base_dps.mean,
+            dps90: self.base_sketch.percentile(90.0),
+            weights,
+        }
+    }
+}
+
+impl StatWeights {
+    /// Fill the legacy `dps_*` fields, which the UI reads as iteration-weighted sums
+    pub fn fill_legacy(&self, results: &mut SimulationsResult) {
+        let n = self.iterations as f64;
+        let step_dps = |stat: Stat| self.weights.iter().find(|w| w.stat == stat).map_or((f64::NAN, f64::NAN), |w| (w.dps, w.dps90));
+        let (sp, sp90) = step_dps(Stat::SpellPower);
+        let (crit, crit90) = step_dps(Stat::Crit);
+        let (hit, hit90) = step_dps(Stat::Hit);
+        results.dps_select = n * self.dps;
+        results.dps_sp = n * sp;
+        results.dps_crit = n * crit;
+        results.dps_hit = n * hit;
+        results.dps90_select = n * self.dps90;
+        results.dps90_sp = n * sp90;
+        results.dps90_crit = n * crit90;
+        results.dps90_hit = n * hit90;
+    }
+}
+
+/// DPS change of every mage and of the raid when one mage gets one stat step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatWeightRow {
+    pub player: usize,
+    pub stat: Stat,
+    pub step: f64,
+    pub players: Vec<Estimate>,
+    pub raid: Estimate,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatWeightMatrix {
+    pub iterations: i32,
+    pub names: Vec<String>,
+    /// Baseline per-player and raid DPS
+    pub dps: Vec<f64>,
+    pub raid_dps: f64,
+    /// One row per (player, step), players outer
+    pub rows: Vec<StatWeightRow>,
+}
+
+impl StatWeightMatrix {
+    /// Player whose upgrade in `stat` adds the most raid DPS
+    pub fn best_recipient(&self, stat: Stat) -> Option<usize> {
+        self.rows.iter()
+            .filter(|r| r.stat == stat)
+            .max_by(|a, b| a.raid.mean.total_cmp(&b.raid.mean))
+            .map(|r| r.player)
+    }
+}
+
+/// Perturb each mage in turn and measure everyone. Shared debuffs (ignite, scorch)
+/// show up as off-diagonal entries. Invalid steps are skipped.
+pub fn run_stat_weight_matrix<D, F>(params: &SimParams, make_decider: F, iterations: i32, seed: u64, steps: &[StatStep]) -> StatWeightMatrix
+where
+    D: Decider,
+    F: Fn() -> D,
+{
+    let steps: Vec<StatStep> = steps.iter().copied().filter(StatStep::is_valid).collect();
+    let nm = params.config.num_mages;
+    let mut perturbed = Vec::with_capacity(nm * steps.len());
+    for player in 0..nm {
+        for s in &steps {
+            let mut p = params.clone();
+            s.stat.apply(&mut p.stats, &[player], s.step);
+            perturbed.push((player, *s, p));
+        }
+    }
+
+    let mut base_players = vec![RunningStats::default(); nm];
+    let mut base_raid = RunningStats::default();
+    let mut deltas = vec![vec![RunningStats::default(); nm]; perturbed.len()];
+    let mut raid_deltas = vec![RunningStats::default(); perturbed.len()];
+
+    for idx in 1..=iterations {
+        let base = run_single_dps(params, &mut make_decider(), seed, idx as u64);
+        base_raid.push(base.dps);
+        for (stats, pr) in base_players.iter_mut().zip(base.players.iter()) { stats.push(pr.dps); }
+
+        for (rdx, (_, _, p)) in perturbed.iter().enumerate() {
+            let result = run_single_dps(p, &mut make_decider(), seed, idx as u64);
+            raid_deltas[rdx].push(result.dps - base.dps);
+            for (j, (pr, pb)) in result.players.iter().zip(base.players.iter()).enumerate() {
+                deltas[rdx][j].push(pr.dps - pb.dps);
+            }
+        }
+    }
+
+    let rows = perturbed.iter().enumerate()
+        .map(|(rdx, (player, s, _))| StatWeightRow {
+            player: *player,
+            stat: s.stat,
+            step: s.step,
+            players: deltas[rdx].iter().map(Estimate::from).collect(),
+            raid: Estimate::from(&raid_deltas[rdx]),
+        })
+        .collect();
+
+    StatWeightMatrix {
+        iterations,
+        names: params.config.name.clone(),
+        dps: base_players.iter().map(|s| s.mean).collect(),
+        raid_dps: base_raid.mean,
+        rows,
+    }
+}
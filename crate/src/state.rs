@@ -3,11 +3,13 @@
 use core::f64;
 
 use log::debug;
-use rand::Rng;
-use rand_chacha::ChaCha8Rng;
+use crate::rng::{Mechanic, SimRng};
 use crate::constants::{self as C, Buff, TeamTalentPoints};
 use crate::constants::{Action, Spell, Constants};
-use crate::orchestration::{DamageAccumulator, LogEntry, LogType, SpellResult};
+use crate::orchestration::{DamageAccumulator, SpellResult};
+use crate::events::{CombatEvent, EventKind, LaneAuras, TargetDebuffs};
+use crate::metrics::{Activity, ActivityInterval, Aura, Cooldown, Metrics, COOLDOWNS};
+use crate::constants::DamageSource;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Totals {
@@ -31,6 +33,7 @@ pub struct Boss {
     pub ignite_count: u8,
     pub ignite_value: f64,
     pub ignite_multiplier: f64,
+    pub ignite_owner: usize, // lane whose crit started the current ignite
     pub tick_timer: f64,
     pub scorch_timer: f64,
     pub scorch_count: u8,
@@ -51,6 +54,7 @@ impl Default for Boss {
             ignite_count: 0,
             ignite_value: 0.0,
             ignite_multiplier: 1.0,
+            ignite_owner: 0,
             tick_timer: f64::INFINITY,
             scorch_timer: 0.0,
             scorch_count: 0,
@@ -159,6 +163,73 @@ pub struct PlayerMeta {
     pub talents: TeamTalentPoints,
     pub no_debuff_limit: bool,
     pub name: Vec<String>,
+    pub cast_time: Vec<[f64; C::NUM_SPELLS]>,    // per-lane copies of the Constants timings,
+    pub spell_travel: Vec<[f64; C::NUM_SPELLS]>, // so deciders can read them
+}
+
+/// APL spell ids of the player cooldowns, player auras and target debuffs that
+/// deciders can read with reaction time; the `watched_*` accessors below are the
+/// one place these ids map to timers
+pub const WATCHED_COOLDOWNS: [i32; 10] = [29977, 10199, 10060, 12042, 12043, 20554, 28779, 23271, 24658, 23723];
+pub const WATCHED_AURAS: [i32; 9] = [29977, 10060, 12042, 12043, 20554, 28779, 23271, 24658, 23723];
+pub const WATCHED_DEBUFFS: [i32; 2] = [22959, 12654];
+
+/// A watched state, when it was entered and when the state before it was
+#[derive(Debug, Clone, Copy)]
+pub struct Flip {
+    pub active: bool,
+    pub at: f64,
+    pub prev: f64,
+}
+
+/// Last two on/off changes of everything deciders can watch; filled on the first
+/// `subtime` with `watch_enabled`. Left empty, the `*_seen` reads see the current state.
+#[derive(Debug, Clone, Default)]
+pub struct Watched {
+    pub cooldowns: Vec<[Flip; WATCHED_COOLDOWNS.len()]>,
+    pub auras: Vec<[Flip; WATCHED_AURAS.len()]>,
+    pub debuffs: Vec<Flip>,
+}
+
+fn watched_buff(id: i32) -> Option<Buff> {
+    match id {
+        28779 => Some(Buff::Sapp),
+        23271 => Some(Buff::Toep),
+        24658 => Some(Buff::Zhc),
+        23723 => Some(Buff::Mqg),
+        _ => None,
+    }
+}
+
+/// When a watched timer now reading `timer` left `flip`'s state: run-out timers are
+/// dated by how far they went below zero, every other change to `now`
+fn change_time(flip: Flip, timer: f64, now: f64) -> f64 {
+    let t = if timer <= 0.0 && timer.is_finite() { now + timer } else { now };
+    t.max(flip.at)
+}
+
+/// Date a change of a watched timer since `flip` was last updated
+fn update_flip(flip: &mut Flip, timer: f64, now: f64) {
+    let active = timer > 0.0;
+    if active != flip.active {
+        *flip = Flip { active, at: change_time(*flip, timer, now), prev: flip.at };
+    }
+}
+
+/// Whether a watched timer now reading `timer` looked active `reaction` seconds ago.
+/// Knows the two dated changes plus one since the last `watch`, so e.g. an Ignite
+/// that dropped and came back inside the window reads right.
+fn seen_active(flip: Flip, timer: f64, now: f64, reaction: f64) -> bool {
+    let active = timer > 0.0;
+    let changes = if active != flip.active {
+        [change_time(flip, timer, now), flip.at, flip.prev]
+    } else {
+        [flip.at, flip.prev, f64::NEG_INFINITY]
+    };
+    // going back in time from now, every change passed flips the state
+    let seen = now - reaction;
+    let passed = changes.iter().take_while(|&&t| seen < t).count();
+    active != (passed % 2 == 1)
 }
 
 #[derive(Debug, Clone)]
@@ -169,169 +240,271 @@ pub struct State {
     pub meta: PlayerMeta,
     pub totals: Totals,
     pub log_enabled: bool,
-    pub log: Vec<LogEntry>,
+    /// Off for runs that only read DPS (stat weights, tuning, rollouts)
+    pub metrics_enabled: bool,
+    pub events: Vec<CombatEvent>,
     pub damage_log: Vec<DamageAccumulator>,
+    pub metrics: Metrics,
+    pub timeline: Vec<ActivityInterval>, // only filled when log_enabled
+    /// Date watched changes in `subtime`; on once a decider reads React values
+    pub watch_enabled: bool,
+    pub watched: Watched,
 }
 
-fn combustion_string(mage_line: &mut MageLane) -> String {
-    if mage_line.comb_left > 0 {
-        return format!("stack: {}  remain: {}", mage_line.comb_stack, mage_line.comb_left);
-    } else if mage_line.comb_cooldown <= 0.0 {
-        return format!("off cooldown")
+/// Seconds until `cd` is ready on this lane; negative once ready, infinite if unavailable
+fn cooldown_remaining(l: &MageLane, cd: Cooldown) -> f64 {
+    match cd {
+        Cooldown::Combustion => l.comb_cooldown,
+        Cooldown::Sapp => l.buff_cooldown[Buff::Sapp as usize],
+        Cooldown::Toep => l.buff_cooldown[Buff::Toep as usize],
+        Cooldown::Zhc => l.buff_cooldown[Buff::Zhc as usize],
+        Cooldown::Mqg => l.buff_cooldown[Buff::Mqg as usize],
+        Cooldown::PowerInfusion => l.pi_cooldown.iter().copied().fold(f64::INFINITY, f64::min),
+        Cooldown::ArcanePower => l.ap_cooldown,
+        Cooldown::PresenceOfMind => l.pom_cooldown,
+        Cooldown::Berserking => l.berserk_cooldown,
     }
-    format!("cooldown: {:.2}", mage_line.comb_cooldown)
 }
 
-fn buff_string(mage_lane: &mut MageLane) -> String {
-    let mut buffs: String = "".to_owned();
-    if mage_lane.buff_timer[Buff::Sapp as usize] > 0.0 {
-        buffs.push_str("Sapp");
-    } else {
-        buffs.push_str("    ");
+impl State {
+    pub fn new(duration: f64, num_mages: usize) -> Self {
+        Self {
+            global: Global::new(duration),
+            boss: Boss::default(),
+            lanes: vec![MageLane::default(); num_mages],
+            meta: PlayerMeta::default(),
+            totals: Totals::default(),
+            log_enabled: false,
+            metrics_enabled: true,
+            events: vec![],
+            damage_log: vec![],
+            metrics: Metrics::new(num_mages),
+            timeline: vec![],
+            watch_enabled: false,
+            watched: Watched::default(),
+        }
     }
-    if mage_lane.buff_timer[Buff::Toep as usize] > 0.0 {
-        buffs.push_str("Toep");
-    } else {
-        buffs.push_str("    ");
+
+    /// Remaining time of a watched cooldown by APL spell id: positive while on
+    /// cooldown, minus the time since it came off otherwise
+    pub fn watched_cooldown(&self, lane: usize, id: i32) -> f64 {
+        let l = &self.lanes[lane];
+        let cd = match id {
+            29977 => Cooldown::Combustion,
+            10199 => return l.fb_cooldown, // FIRE_BLAST
+            10060 => Cooldown::PowerInfusion,
+            12042 => Cooldown::ArcanePower,
+            12043 => Cooldown::PresenceOfMind,
+            20554 => Cooldown::Berserking,
+            28779 => Cooldown::Sapp,
+            23271 => Cooldown::Toep,
+            24658 => Cooldown::Zhc,
+            23723 => Cooldown::Mqg,
+            _ => return 0.0,
+        };
+        cooldown_remaining(l, cd)
     }
-    if mage_lane.buff_timer[Buff::Zhc as usize] > 0.0 {
-        buffs.push_str("ZHC");
-    } else {
-        buffs.push_str("   ");
+
+    /// Remaining time of a watched player aura by APL spell id; auras that end on
+    /// use (Combustion charges, Presence of Mind) read infinite while up
+    pub fn watched_aura(&self, lane: usize, id: i32) -> f64 {
+        let l = &self.lanes[lane];
+        match id {
+            29977 => if l.comb_left > 0 { f64::INFINITY } else { 0.0 },
+            10060 => l.pi_timer.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            12042 => l.ap_timer,
+            12043 => if l.pom_active { f64::INFINITY } else { 0.0 },
+            20554 => l.berserk_timer,
+            _ => match watched_buff(id) {
+                Some(buff) => l.buff_timer[buff as usize],
+                None => 0.0,
+            },
+        }
     }
-    if mage_lane.buff_timer[Buff::Mqg as usize] > 0.0 {
-        buffs.push_str("MQG");
-    } else {
-        buffs.push_str("   ");
+
+    /// Remaining time of a watched target debuff by APL spell id
+    pub fn watched_debuff(&self, id: i32) -> f64 {
+        match id {
+            22959 => self.boss.scorch_timer, // FIRE_VULNERABILITY
+            12654 => self.boss.ignite_timer,
+            _ => 0.0,
+        }
     }
-    if mage_lane.pi_timer.iter().cloned().reduce(f64::max).unwrap() > 0.0 {
-        buffs.push_str("PI");
-    } else {
-        buffs.push_str("  ");
+
+    /// Date every watched change since the last call; the first call takes the
+    /// current states as the starting ones
+    fn watch(&mut self) {
+        let now = self.global.running_time;
+        if self.watched.cooldowns.len() != self.lanes.len() {
+            let flip = |timer: f64| Flip { active: timer > 0.0, at: f64::NEG_INFINITY, prev: f64::NEG_INFINITY };
+            self.watched = Watched {
+                cooldowns: (0..self.lanes.len()).map(|lane| WATCHED_COOLDOWNS.map(|id| flip(self.watched_cooldown(lane, id)))).collect(),
+                auras: (0..self.lanes.len()).map(|lane| WATCHED_AURAS.map(|id| flip(self.watched_aura(lane, id)))).collect(),
+                debuffs: WATCHED_DEBUFFS.iter().map(|&id| flip(self.watched_debuff(id))).collect(),
+            };
+            return;
+        }
+        for lane in 0..self.lanes.len() {
+            for (i, &id) in WATCHED_COOLDOWNS.iter().enumerate() {
+                let timer = self.watched_cooldown(lane, id);
+                update_flip(&mut self.watched.cooldowns[lane][i], timer, now);
+            }
+            for (i, &id) in WATCHED_AURAS.iter().enumerate() {
+                let timer = self.watched_aura(lane, id);
+                update_flip(&mut self.watched.auras[lane][i], timer, now);
+            }
+        }
+        for (i, &id) in WATCHED_DEBUFFS.iter().enumerate() {
+            let timer = self.watched_debuff(id);
+            update_flip(&mut self.watched.debuffs[i], timer, now);
+        }
     }
-    if mage_lane.ap_timer > 0.0 {
-        buffs.push_str("AP");
-    } else {
-        buffs.push_str("  ");
+
+    /// Whether `lane` saw its cooldown `id` running, `reaction` seconds late
+    pub fn cooldown_seen(&self, lane: usize, id: i32, reaction: f64) -> bool {
+        let timer = self.watched_cooldown(lane, id);
+        let flip = WATCHED_COOLDOWNS.iter().position(|&w| w == id)
+            .and_then(|i| Some(self.watched.cooldowns.get(lane)?[i]));
+        flip.map_or(timer > 0.0, |f| seen_active(f, timer, self.global.running_time, reaction))
     }
-    if mage_lane.pom_active {
-        buffs.push_str("PoM");
-    } else {
-        buffs.push_str("  ");
+
+    /// Whether `lane` saw its aura `id` up, `reaction` seconds late
+    pub fn aura_seen(&self, lane: usize, id: i32, reaction: f64) -> bool {
+        let timer = self.watched_aura(lane, id);
+        let flip = WATCHED_AURAS.iter().position(|&w| w == id)
+            .and_then(|i| Some(self.watched.auras.get(lane)?[i]));
+        flip.map_or(timer > 0.0, |f| seen_active(f, timer, self.global.running_time, reaction))
     }
-    if mage_lane.berserk_timer > 0.0 {
-        buffs.push_str("BSK");
-    } else {
-        buffs.push_str("  ");
+
+    /// Whether the target debuff `id` was seen up, `reaction` seconds late
+    pub fn debuff_seen(&self, id: i32, reaction: f64) -> bool {
+        let timer = self.watched_debuff(id);
+        let flip = WATCHED_DEBUFFS.iter().position(|&w| w == id)
+            .and_then(|i| self.watched.debuffs.get(i).copied());
+        flip.map_or(timer > 0.0, |f| seen_active(f, timer, self.global.running_time, reaction))
     }
-    buffs
-}
 
-fn debuff_string(boss: &mut Boss, dragonling_active: bool) -> String {
-    let mut debuffs: String = "".to_owned();
-    
-    if boss.scorch_timer > 0.0 {
-        debuffs.push_str(format!("scorch:{}({:.2}) ", boss.scorch_count, boss.scorch_timer).as_str());
-    } else {
-        debuffs.push_str("scorch:0 ");
+    /// Event skeleton with the running DPS and the aura / debuff snapshot filled in
+    fn new_event(&self, kind: EventKind, lane: usize, delay: f64) -> CombatEvent {
+        let now = self.global.running_time;
+        CombatEvent {
+            t: now + delay,
+            kind,
+            lane: Some(lane),
+            partial: 1.0,
+            raid_dps: if now > 0.0 { self.totals.total_damage / now } else { 0.0 },
+            ignite_dps: if now > 0.0 { self.totals.ignite_damage / now } else { 0.0 },
+            auras: if kind == EventKind::IgniteTick { None } else { Some(LaneAuras::of(&self.lanes[lane])) },
+            debuffs: TargetDebuffs::of(&self.boss, now),
+            ..Default::default()
+        }
     }
-    if boss.ignite_timer > 0.0 {
-        debuffs.push_str(format!("ignite:{}({:.2})[{:.0}|{:.2}] ", boss.ignite_count, boss.ignite_timer, boss.ignite_value, boss.ignite_multiplier).as_str());
-    } else {
-        debuffs.push_str("ignite:0 ");
+
+    pub fn log_cast(&mut self, kind: EventKind, lane: usize, action: Action, delay: f64) {
+        let event = CombatEvent { action: Some(action), ..self.new_event(kind, lane, delay) };
+        self.events.push(event);
     }
-    if boss.wc_timer > 0.0 {
-        debuffs.push_str(format!("wc:{}({:.2}) ", boss.wc_count, boss.wc_timer).as_str());
-    } // no else.  this is a fire simulator
-    if dragonling_active {
-        debuffs.push_str("dragonling ");
+
+    pub fn log_tick(&mut self, value: f64, partial: f64) {
+        let event = CombatEvent {
+            source: Some(DamageSource::Ignite),
+            amount: value,
+            partial,
+            result: SpellResult::Hit,
+            ..self.new_event(EventKind::IgniteTick, self.boss.ignite_owner, 0.0)
+        };
+        self.events.push(event);
     }
-    if boss.spell_vulnerability > 0.0 {
-        debuffs.push_str(format!("nightfall:{:.2}", boss.spell_vulnerability).as_str());
+
+    pub fn log_spell_impact(&mut self, lane: usize, spell: Spell, value: f64, partial: f64, result: SpellResult) {
+        let kind = if spell == Spell::PyroDot { EventKind::PeriodicDamage } else { EventKind::SpellImpact };
+        let event = CombatEvent {
+            source: Some(spell.into()),
+            amount: value,
+            partial,
+            result,
+            ..self.new_event(kind, lane, 0.0)
+        };
+        self.events.push(event);
     }
-    debuffs
-}
 
-impl State {
-    pub fn new(duration: f64, num_mages: usize) -> Self {
-        Self {
-            global: Global::new(duration),
-            boss: Boss::default(),
-            lanes: vec![MageLane::default(); num_mages],
-            meta: PlayerMeta::default(),
-            totals: Totals::default(),
-            log_enabled: false,
-            log: vec![],
-            damage_log: vec![],
+    // ---------- time & scheduling ----------
+    /// Add the part of the next `dt` seconds that falls inside the fight to the uptimes
+    fn record_uptime(&mut self, dt: f64) {
+        let now = self.global.running_time;
+        let dt = dt.min(self.global.duration - now);
+        if dt <= 0.0 || !self.metrics_enabled { return; }
+        let within = |timer: f64| timer.clamp(0.0, dt);
+
+        let b = &self.boss;
+        let u = &mut self.metrics.uptime;
+        u.scorch[b.scorch_count as usize] += within(b.scorch_timer);
+        u.ignite[b.ignite_count as usize] += within(b.ignite_timer);
+        u.winters_chill += within(b.wc_timer);
+        u.nightfall += within(b.spell_vulnerability);
+        let dragonling_end = b.dragonling_start + C::DRAGONLING_DURATION;
+        u.dragonling += (dragonling_end.min(now + dt) - b.dragonling_start.max(now)).max(0.0);
+
+        for (l, times) in self.lanes.iter().zip(u.lanes.iter_mut()) {
+            times[Aura::Sapp as usize] += within(l.buff_timer[Buff::Sapp as usize]);
+            times[Aura::Toep as usize] += within(l.buff_timer[Buff::Toep as usize]);
+            times[Aura::Zhc as usize] += within(l.buff_timer[Buff::Zhc as usize]);
+            times[Aura::Mqg as usize] += within(l.buff_timer[Buff::Mqg as usize]);
+            times[Aura::PowerInfusion as usize] += within(l.pi_timer.iter().copied().fold(0.0, f64::max));
+            times[Aura::ArcanePower as usize] += within(l.ap_timer);
+            times[Aura::Berserking as usize] += within(l.berserk_timer);
+            if l.comb_left > 0 { times[Aura::Combustion as usize] += dt; }
         }
     }
 
-    pub fn log_cast(&mut self, log_type: LogType, unit_id: i32, spell: Action, delay: f64) {
-        let l = &mut self.lanes[unit_id as usize];
-        let dragonling_active = (self.global.running_time >= self.boss.dragonling_start) && (self.global.running_time < self.boss.dragonling_start + C::DRAGONLING_DURATION);
-        let b = &mut self.boss;
-        self.log.push(LogEntry {
-            log_type: log_type,
-            text: format!("s[{}]", spell),
-            unit_name: self.meta.name[unit_id as usize].clone(),
-            t: self.global.running_time + delay,
-            dps: 0.0,
-            total_dps: if self.global.running_time > 0.0 { self.totals.total_damage / self.global.running_time } else { 0.0 },
-            ignite_dps: if self.global.running_time > 0.0 { self.totals.ignite_damage / self.global.running_time } else { 0.0 },
-            value: 0.0,
-            value2: 0.0,
-            spell_result: SpellResult::None,
-            combustion: combustion_string(l),
-            buffs: buff_string(l),
-            debuffs: debuff_string(b, dragonling_active),
-        });
+    /// Book `start..end` (clamped to the fight) of a lane's time to `activity`
+    fn record_activity(&mut self, lane: usize, activity: Activity, start: f64, end: f64) {
+        let start = start.max(0.0);
+        let end = end.min(self.global.duration);
+        if end <= start { return; }
+        if self.metrics_enabled {
+            self.metrics.lanes[lane].activity[activity as usize] += end - start;
+        }
+        if self.log_enabled {
+            self.timeline.push(ActivityInterval { lane, activity, start, end });
+        }
     }
 
-    pub fn log_tick(&mut self, value: f64, partial: f64) {
-        let dragonling_active = (self.global.running_time >= self.boss.dragonling_start) && (self.global.running_time < self.boss.dragonling_start + C::DRAGONLING_DURATION);
-        let b = &mut self.boss;
-        self.log.push(LogEntry {
-            log_type: LogType::IgniteTick,
-            text: format!("a[Ignite] -> t[{:.0}]", value),
-            unit_name: format!(""),
-            t: self.global.running_time,
-            dps: 0.0,
-            total_dps: if self.global.running_time > 0.0 { self.totals.total_damage / self.global.running_time } else { 0.0 },
-            ignite_dps: if self.global.running_time > 0.0 { self.totals.ignite_damage / self.global.running_time } else { 0.0 },
-            value: value,
-            value2: (1.0 - partial)*value,
-            spell_result: SpellResult::Hit,
-            combustion: String::new(),
-            buffs: String::new(),
-            debuffs: debuff_string(b, dragonling_active),
-        });
-    }
-
-    pub fn log_spell_impact(&mut self, unit_id: i32, spell: Spell, value: f64, partial: f64, result: SpellResult) {
-
-        let l = &mut self.lanes[unit_id as usize];
-        let dragonling_active = (self.global.running_time >= self.boss.dragonling_start) && (self.global.running_time < self.boss.dragonling_start + C::DRAGONLING_DURATION);
-        let b = &mut self.boss;
-        self.log.push(LogEntry {
-
-            log_type: LogType::SpellImpact,
-            text: format!("s[{}] -> t[{:.0}]", spell, value),
-            unit_name: self.meta.name[unit_id as usize].clone(),
-            t: self.global.running_time,
-            dps: 0.0,
-            total_dps: if self.global.running_time > 0.0 { self.totals.total_damage / self.global.running_time } else { 0.0 },
-            ignite_dps: if self.global.running_time > 0.0 { self.totals.ignite_damage / self.global.running_time } else { 0.0 },
-            value: value,
-            value2: (1.0 - partial)*value,
-            spell_result: result,
-            combustion: combustion_string(l),
-            buffs: buff_string(l),
-            debuffs: debuff_string(b, dragonling_active),
-        });
+    /// Close out metrics of events still open when the fight ends
+    pub fn finish_metrics(&mut self) {
+        if !self.metrics_enabled { return; }
+
+        // an ignite that ran out and was never restarted
+        if self.boss.ignite_count > 0 && self.boss.ignite_timer <= 0.0 {
+            let expired_at = self.global.running_time + self.boss.ignite_timer;
+            if expired_at < self.global.duration {
+                self.metrics.ignite.record_drop(self.global.duration - expired_at);
+            }
+        }
+
+        // whatever was not casting, GCD or reaction was idle
+        for m in self.metrics.lanes.iter_mut() {
+            let busy: f64 = m.activity[..Activity::Idle as usize].iter().sum();
+            m.activity[Activity::Idle as usize] = (self.global.duration - busy).max(0.0);
+        }
+
+        // charges left unspent and cooldowns left sitting ready
+        for (l, m) in self.lanes.iter().zip(self.metrics.lanes.iter_mut()) {
+            m.cooldowns[Cooldown::Combustion as usize].wasted += l.comb_left as u32;
+            m.cooldowns[Cooldown::PresenceOfMind as usize].wasted += l.pom_active as u32;
+            for cd in COOLDOWNS {
+                let remaining = cooldown_remaining(l, cd);
+                if remaining.is_finite() {
+                    let ready_at = self.global.running_time + remaining;
+                    m.cooldowns[cd as usize].ready_at_end += (self.global.duration - ready_at).max(0.0);
+                }
+            }
+        }
     }
 
-    // ---------- time & scheduling ----------
     pub fn subtime(&mut self, dt: f64) {
+        if self.watch_enabled { self.watch(); }
+        self.record_uptime(dt);
         self.global.running_time += dt;
         self.boss.ignite_timer -= dt;
         self.boss.tick_timer -= dt;
@@ -397,10 +570,32 @@ impl State {
     pub fn decision_gate(&self) -> bool { self.global.decision_gate }
     pub fn in_progress(&self) -> bool { self.global.running_time < self.global.duration }
 
+    /// Cast time multiplier from MQG and Berserking
+    pub fn haste_factor(&self, lane: usize) -> f64 {
+        let l = &self.lanes[lane];
+        let mut haste: f64 = 1.0;
+        haste /= if l.buff_timer[Buff::Mqg as usize] > 0.0 { 1.0 + C::MQG_HASTE } else { 1.0 };
+        haste *= if l.berserk_timer > 0.0 { 1.0 - self.meta.berserk_slots[lane] } else { 1.0 };
+        haste
+    }
+
+    /// Cast time `lane` would get for `spell` if it started now: hasted, 0 under PoM
+    pub fn spell_cast_time(&self, lane: usize, spell: Spell) -> f64 {
+        if self.lanes[lane].pom_active { return 0.0; }
+        let base = self.meta.cast_time.get(lane).map_or(0.0, |t| t[spell as usize]);
+        base * self.haste_factor(lane)
+    }
+
+    /// Travel time from cast end to impact of `spell` for `lane`
+    pub fn spell_travel_time(&self, lane: usize, spell: Spell) -> f64 {
+        self.meta.spell_travel.get(lane).map_or(0.0, |t| t[spell as usize])
+    }
+
     /// Called by the decider mapping of _apply_decisions → start_action
     pub fn start_action(&mut self, lane: usize, action: Action, continuing_delay: f64, k: &[Constants]) {
-        use crate::constants::{Action as A, Buff as B, Spell as S};
+        use crate::constants::{Action as A, Spell as S};
 
+        let haste = self.haste_factor(lane);
         let l = &mut self.lanes[lane];
         let k_lane = &k[lane];
 
@@ -427,9 +622,6 @@ impl State {
                 cast_time = 0.0;
                 l.pom_active = false;
             } else {
-                let mut haste: f64 = 1.0;
-                haste /= if l.buff_timer[B::Mqg as usize] > 0.0 { 1.0 + C::MQG_HASTE } else { 1.0 };
-                haste *= if l.berserk_timer > 0.0 { 1.0 - self.meta.berserk_slots[lane] } else { 1.0 };
                 cast_time *= haste;
             }
 
@@ -441,15 +633,23 @@ impl State {
         } else {
             l.gcd_timer = 0.0;
         }
+        let now = self.global.running_time;
+        let cast_end = now + self.lanes[lane].cast_timer;
+        if action == A::Gcd {
+            self.record_activity(lane, Activity::Idle, now, cast_end);
+        } else {
+            self.record_activity(lane, Activity::Reaction, now, now + continuing_delay);
+            self.record_activity(lane, Activity::Casting, now + continuing_delay, cast_end);
+        }
         if self.log_enabled {
-            self.log_cast(LogType::CastStart, lane as i32, action, continuing_delay);    
+            self.log_cast(EventKind::CastStart, lane, action, continuing_delay);    
         }
 
         // Block decisions until the event is handled (Python clears global decision flag)
         self.set_decision_gate(false);
     }
 
-    pub fn finish_cast(&mut self, k: &[Constants], rng: &mut ChaCha8Rng) {
+    pub fn finish_cast(&mut self, k: &[Constants], rng: &mut SimRng) {
         use crate::constants::{Action as A, Buff as B, Spell as S};
 
         // Which lane just finished its cast?
@@ -474,6 +674,7 @@ impl State {
                 A::Frostbolt => S::Frostbolt,
                 _ => S::Scorch, // safe default; you can refine
             };
+            if self.metrics_enabled { self.metrics.record_cast(lane, spell.into()); }
 
             // Find the first available slot in the spell queue (timer == f64::INFINITY)
             if let Some(slot) = l.spell_timer.iter().position(|&t| t == f64::INFINITY) {
@@ -489,19 +690,28 @@ impl State {
             let is_t2_8p = self.meta.t2_8p_slots.iter().any(|&i| i == lane);
             if is_t2_8p && k_lane.spell_trigger_t2_8p[spell as usize] {
                 // inaccuracy: multiple t2 procs will not be GCD interlaced
-                if rng.r#gen::<f64>() < C::T2_8P_CHANCE {
+                if rng.roll(lane, Mechanic::T2Proc) < C::T2_8P_CHANCE {
                     let mut l_gcd = C::GLOBAL_COOLDOWN;
                     if l.gcd_timer > 0.0 {
                         l_gcd += l.gcd_timer;
                     }
                     l.gcd_timer = l_gcd;
                     l.cast_timer = 0.0;
-                    self.log_cast(LogType::CastSuccess, lane as i32, action, 0.0); 
+                    if self.log_enabled {
+                        self.log_cast(EventKind::CastSuccess, lane, action, 0.0);
+                    }
                     return;
                 }
             }
         } else {
 
+            // PI cast into Arcane Power does nothing and keeps its cooldown
+            let pi_blocked = action == A::PowerInfusion && l.ap_timer > 0.0;
+            if let Some(cd) = Cooldown::from_action(action).filter(|_| self.metrics_enabled && !pi_blocked) {
+                let delay = (-cooldown_remaining(l, cd)).max(0.0);
+                self.metrics.lanes[lane].cooldowns[cd as usize].record_use(self.global.running_time, delay);
+            }
+
             // 2) apply instant casts’ effects immediately (Python “apply instant spells” block)
             match action {
                 A::Combustion => {
@@ -584,19 +794,22 @@ impl State {
         }
 
         // ---- now we can borrow &mut self again safely ----
-        if !push_gcd {
+        if push_gcd {
+            let now = self.global.running_time;
+            self.record_activity(lane, Activity::GcdLock, now, now + self.lanes[lane].cast_timer);
+        } else {
             self.set_decision_gate(true);
             let cn = self.lanes[lane].cast_number;
             self.lanes[lane].cast_number = cn.saturating_add(1);
         }
         if self.log_enabled {
-            self.log_cast(LogType::CastSuccess, lane as i32, action, 0.0); 
+            self.log_cast(EventKind::CastSuccess, lane, action, 0.0); 
         }
 
     }
 
     // ---------- mechanics: landing & effects (faithful to Python) ----------
-    pub fn land_spell(&mut self, k: &[Constants], rng: &mut ChaCha8Rng) {
+    pub fn land_spell(&mut self, k: &[Constants], rng: &mut SimRng) {
         let Some(lane) = self.next_spell_lane() else { return };
         
         // Find the spell slot with the minimum timer in this lane
@@ -622,10 +835,21 @@ impl State {
         // Clear the processed spell slot
         l.spell_timer[slot] = f64::INFINITY;
 
+        // Draw every roll of the spell up front, whether it lands, crits or procs or not,
+        // so a stat change that flips one outcome leaves the later draws of each stream alone
+        let hit_roll = rng.roll(lane, Mechanic::Hit);
+        let base_roll = rng.roll(lane, Mechanic::DamageRoll);
+        let resist_roll = rng.roll(lane, Mechanic::PartialResist);
+        let crit_roll = rng.roll(lane, Mechanic::Crit);
+        let scorch_roll = rng.roll(lane, Mechanic::ScorchProc);
+        let wc_roll = rng.roll(lane, Mechanic::WintersChill);
+        let t3_roll = rng.roll(lane, Mechanic::T3Proc);
+
         // use the stashed values instead of reading through `l` where possible
-        if rng.r#gen::<f64>() >= lane_hit {
+        if hit_roll >= lane_hit {
+            if self.metrics_enabled { self.metrics.record_miss(lane, spell_string.into()); }
             if self.log_enabled {
-                self.log_spell_impact(lane as i32, spell_string, 0.0, 0.0, SpellResult::Miss);
+                self.log_spell_impact(lane, spell_string, 0.0, 0.0, SpellResult::Miss);
             }
             return;
         }
@@ -646,12 +870,11 @@ impl State {
             // see proc later.  this debuff confirmed can be consumed and applied in same spell hit
         }
 
-        let base_roll: f64 = rng.r#gen();
         let mut spell_damage = k_lane.spell_base[spell_type] + base_roll*k_lane.spell_range[spell_type];
         let mut partial: f64 = 1.0;
         if is_fire {
             spell_damage += k_lane.sp_multiplier[spell_type]*(l.fire_power + buff_damage);
-            let r: f64 = rng.r#gen();
+            let r: f64 = resist_roll;
             partial = if r < C::RES_THRESH[1] { C::RES_AMOUNT[0] } else if r < C::RES_THRESH[2] { C::RES_AMOUNT[1] } else if r < C::RES_THRESH[3] { C::RES_AMOUNT[2] } else { C::RES_AMOUNT[3] };
             spell_damage *= partial;
         } else {
@@ -679,17 +902,19 @@ impl State {
         let comb_bonus = if is_fire && l.comb_left > 0 { C::PER_COMBUSTION * (l.comb_stack as f64) } else { 0.0 };
         let wc_bonus: f64 = if !is_fire && self.boss.wc_timer > 0.0 { C::PER_WC * self.boss.wc_count as f64 } else { 0.0 };
         let crit_chance = (if is_fire { l.crit_chance_fire } else { l.crit_chance_frost } + comb_bonus + wc_bonus + k_lane.incin_bonus[spell_type]).clamp(0.0, 1.0);
-        let is_crit = rng.r#gen::<f64>() < crit_chance;
+        let is_crit = crit_roll < crit_chance;
 
         if is_crit {
             if is_fire {
                 if k_lane.is_ignite {
                     // ignite timer checks
                     if self.boss.ignite_timer <= 0.0 {
+                        if self.metrics_enabled && self.boss.ignite_count > 0 { self.metrics.ignite.record_drop(-self.boss.ignite_timer); }
                         self.boss.ignite_count = 0;
                         self.boss.ignite_value = 0.0;
                     }
                     if self.boss.ignite_timer < C::DECISION_POINT { l.crit_too_late = true; }
+                    if self.metrics_enabled && self.boss.ignite_timer > 0.0 && self.boss.ignite_timer < C::DECISION_POINT { self.metrics.ignite.late_refreshes += 1; }
                     if self.boss.tick_timer > C::IGNITE_TICK && self.boss.ignite_count > 0 { self.boss.tick_timer = C::IGNITE_TICK; }
                     if self.boss.ignite_timer > 0.0 {
                         self.boss.ignite_refresh_history.push(self.global.running_time);
@@ -701,6 +926,12 @@ impl State {
                     self.boss.ignite_timer = C::IGNITE_TIME + 1e-6;
 
                     if self.boss.ignite_count == 0 {
+                        if self.metrics_enabled {
+                            let m = &mut self.metrics.ignite;
+                            if m.ignites > 0 && self.boss.ignite_owner != lane { m.owner_changes += 1; }
+                            m.ignites += 1;
+                        }
+                        self.boss.ignite_owner = lane;
                         self.boss.tick_timer = C::IGNITE_TICK;
                         let pi_mult = if l.pi_timer.iter().any(|&x| x > 0.0) { 1.0 + C::POWER_INFUSION } else { 1.0 };
                         let dmf_mult = if is_dmf {1.0 + C::DMF_BUFF} else { 1.0 };
@@ -714,6 +945,8 @@ impl State {
                         let ignite_add = crit_mult * k_lane.ignite_damage * spell_damage;
                         if is_cleaner { self.boss.ignite_value += (1.0 + C::UDC_MOD) * ignite_add; } else { self.boss.ignite_value += ignite_add; }
                         self.boss.ignite_count = self.boss.ignite_count.saturating_add(1).min(C::IGNITE_STACK);
+                    } else if self.metrics_enabled {
+                        self.metrics.ignite.cap_refreshes += 1;
                     }
                 }
 
@@ -752,7 +985,7 @@ impl State {
             }
         }
         if k_lane.is_scorch[spell_type] {
-            if rng.r#gen::<f64>() < k_lane.scorch_chance.min(lane_hit) {
+            if scorch_roll < k_lane.scorch_chance.min(lane_hit) {
                 if self.boss.scorch_timer > 0.0 {
                     self.boss.scorch_refresh_history.push(self.global.running_time);
                     if self.boss.scorch_refresh_history.len() > C::MAX_DEBUFF_HISTORY {
@@ -767,7 +1000,7 @@ impl State {
             }
         }
         if !is_fire && k_lane.wc_chance > 0.0 {
-            if rng.r#gen::<f64>() < k_lane.wc_chance.min(lane_hit) {
+            if wc_roll < k_lane.wc_chance.min(lane_hit) {
                 if self.boss.wc_timer <= 0.0 { self.boss.wc_count = 0; }
                 self.boss.wc_timer = C::WC_TIME;
                 self.boss.wc_count = (self.boss.wc_count + 1).min(C::WC_STACK);
@@ -777,7 +1010,7 @@ impl State {
         if self.meta.no_debuff_limit {
             let is_t3_6p = self.meta.t3_6p_slots.iter().any(|&i| i == lane);
             if is_t3_6p {
-                let is_proc = rng.r#gen::<f64>() < C::T3_6P_CHANCE;
+                let is_proc = t3_roll < C::T3_6P_CHANCE;
                 if is_proc {
                     self.boss.t3_6p = C::T3_6P_TIMER;
                 }
@@ -798,12 +1031,13 @@ impl State {
         }
 
         if is_fire { l.comb_stack = l.comb_stack.saturating_add(1); }
+        if self.metrics_enabled { self.metrics.record_damage(lane, spell_string.into(), spell_damage, is_crit, partial); }
 
         if self.log_enabled {
             if is_crit {
-                self.log_spell_impact(lane as i32, spell_string, spell_damage, partial, SpellResult::Crit);
+                self.log_spell_impact(lane, spell_string, spell_damage, partial, SpellResult::Crit);
             } else {
-                self.log_spell_impact(lane as i32, spell_string, spell_damage, partial, SpellResult::Hit);
+                self.log_spell_impact(lane, spell_string, spell_damage, partial, SpellResult::Hit);
             }
         } else {
             self.damage_log.push(DamageAccumulator { time: self.global.running_time, damage: spell_damage});
@@ -811,7 +1045,7 @@ impl State {
 
     }
 
-    pub fn tick_ignite(&mut self, rng: &mut ChaCha8Rng) {
+    pub fn tick_ignite(&mut self, rng: &mut SimRng) {
         // subtime
         let dt = self.boss.tick_timer;
         self.subtime(dt);
@@ -830,11 +1064,15 @@ impl State {
         let mut mult = C::COE_MULTIPLIER * self.boss.ignite_multiplier;
         if self.boss.scorch_timer > 0.0 { mult *= 1.0 + C::SCORCH_MULTIPLIER*(self.boss.scorch_count as f64); }
         if self.boss.spell_vulnerability > 0.0 { mult *= 1.0 + C::NIGHTFALL_VULN; }
-        let r: f64 = rng.r#gen();
+        let r: f64 = rng.roll_raid(Mechanic::IgniteResist);
         let partial: f64 = if r < C::RES_THRESH[1] { C::RES_AMOUNT[0] } else if r < C::RES_THRESH[2] { C::RES_AMOUNT[1] } else if r < C::RES_THRESH[3] { C::RES_AMOUNT[2] } else { C::RES_AMOUNT[3] };
         mult *= partial;
         let ignite_damage = mult * self.boss.ignite_value;
         self.totals.ignite_damage += ignite_damage;
+        if self.metrics_enabled {
+            self.metrics.record_shared_damage(DamageSource::Ignite, ignite_damage, partial);
+            self.metrics.ignite.record_tick(ignite_damage, partial);
+        }
         if self.log_enabled {
             self.log_tick(ignite_damage, partial);
         } else {
@@ -843,7 +1081,7 @@ impl State {
 
     }
 
-    pub fn proc_nightfall(&mut self, rng: &mut ChaCha8Rng) {
+    pub fn proc_nightfall(&mut self, rng: &mut SimRng) {
         // 1) find soonest Nightfall check
         let (idx, dt) = match self.boss.nightfall
             .iter()
@@ -863,7 +1101,7 @@ impl State {
         }
 
         // 4) roll Nightfall proc; if it hits, apply vulnerability window
-        if rng.r#gen::<f64>() < C::NIGHTFALL_PROC_PROB {
+        if rng.roll_raid(Mechanic::Nightfall) < C::NIGHTFALL_PROC_PROB {
             self.boss.spell_vulnerability = C::NIGHTFALL_DURATION;
         }
     }
@@ -888,9 +1126,10 @@ impl State {
         let damage = mult * l.pyro_value;
         self.totals.total_damage += damage;
         l.damage += damage;
+        if self.metrics_enabled { self.metrics.record_damage(lane, DamageSource::PyroDot, damage, false, 1.0); }
 
         if self.log_enabled {
-            self.log_spell_impact(lane as i32, Spell::PyroDot, damage, 1.0, SpellResult::Hit);
+            self.log_spell_impact(lane, Spell::PyroDot, damage, 1.0, SpellResult::Hit);
         } else {
             self.damage_log.push(DamageAccumulator { time: self.global.running_time, damage: damage});
         }
@@ -899,7 +1138,7 @@ impl State {
     /// One discrete simulation step (faithful to mechanics._advance):
     /// choose the nearest event among: cast finish, spell land, ignite tick, nightfall proc
     /// Priority on ties: cast < spell < tick < proc
-    pub fn step_one(&mut self, k: &[Constants], rng: &mut ChaCha8Rng) {
+    pub fn step_one(&mut self, k: &[Constants], rng: &mut SimRng) {
         // Gather next event times
         let cast_t  = self.lanes.iter().map(|l| l.cast_timer).fold(f64::INFINITY, f64::min);
         // Find minimum spell_timer across all lanes and all queued spells
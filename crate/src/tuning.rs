@@ -0,0 +1,212 @@
+//! tuning.rs — search APL `Const` thresholds for the best objective
+//!
+//! A tunable is a `Const` value inside one player's APL JSON, addressed by a JSON
+//! pointer (e.g. "/items/3/condition/values/1"), with a range and step. The tuner
+//! runs coordinate descent: each round sweeps every tunable over its grid with the
+//! others held, keeping the best point. All candidates of a run see the same
+//! iteration seeds, so they are compared on identical fights. The final gain is
+//! re-measured on fresh seeds so the search's own selection doesn't inflate it.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use crate::apl::{create_team_decider_from_apls, AplCondition};
+use crate::decisions::condition_reads_int;
+use crate::orchestration::{iteration_seed, run_single_dps, SimParams};
+use crate::stat_weights::target_dps;
+use crate::stats::{Estimate, QuantileSketch, RunningStats};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tunable {
+    pub player: usize,
+    /// JSON pointer to the `Const` value object within the player's APL
+    pub pointer: String,
+    pub min: f64,
+    pub max: f64,
+    /// Grid spacing; 0 splits the range into four steps
+    pub step: f64,
+}
+
+impl Tunable {
+    /// Grid points; whole numbers only when the constant is read as an int, since
+    /// points rounding to the same int are the same APL
+    fn grid(&self, as_int: bool) -> Vec<f64> {
+        let step = if self.step > 0.0 { self.step } else { (self.max - self.min) / 4.0 };
+        if step <= 0.0 { return vec![if as_int { self.min.round() } else { self.min }]; }
+        let n = ((self.max - self.min) / step + 1e-9).floor() as usize;
+        let mut grid: Vec<f64> = (0..=n).map(|i| self.min + i as f64 * step).collect();
+        if as_int {
+            for x in grid.iter_mut() { *x = x.round(); }
+            grid.dedup();
+        }
+        grid
+    }
+}
+
+/// What the tuner maximises, over the mean DPS of the target players
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Objective {
+    Mean,
+    /// DPS at this percentile (0..=100) of the iterations
+    Percentile(f64),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TuneSpec {
+    pub tunables: Vec<Tunable>,
+    pub objective: Objective,
+    /// Iterations per candidate evaluation
+    pub iterations: i32,
+    /// Coordinate-descent passes over all tunables
+    pub rounds: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TuneResult {
+    /// APLs with the tuned constants written in
+    pub apls: Vec<Option<Value>>,
+    pub start_values: Vec<f64>,
+    pub values: Vec<f64>,
+    /// Objective before and after, measured on the validation seeds
+    pub baseline: f64,
+    pub tuned: f64,
+    /// `tuned - baseline` with its standard error. For `Mean` that is the paired
+    /// per-iteration gain; for `Percentile` the error is bootstrapped
+    pub improvement: Estimate,
+    pub evaluations: usize,
+}
+
+/// Current value of a `Const` APL value: the float if set, else the int. A constant
+/// read as an int prefers the int, as the decider does.
+fn read_const(apls: &[Option<Value>], t: &Tunable, as_int: bool) -> Option<f64> {
+    let v = apls.get(t.player)?.as_ref()?.pointer(&t.pointer)?;
+    let num = |key: &str| v.get(key).and_then(|x| match x {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }).unwrap_or(0.0);
+    Some(if num("vfloat") != 0.0 && !(as_int && num("vint") != 0.0) { num("vfloat") } else { num("vint") })
+}
+
+/// Whether the `Const` at `t.pointer` is read through its `vint` slot, which the
+/// comparison it sits in decides
+fn reads_int(apls: &[Option<Value>], t: &Tunable) -> bool {
+    let Some(Some(apl)) = apls.get(t.player) else { return false };
+    let mut path = String::new();
+    let mut condition = None;
+    for segment in t.pointer.split('/').skip(1) {
+        path.push('/');
+        path.push_str(segment);
+        match apl.pointer(&path) {
+            Some(v) if v.get("condition_type").is_some() => condition = Some(v),
+            Some(_) => {}
+            None => break,
+        }
+    }
+    condition.is_some_and(|c| condition_reads_int(&AplCondition::from(c)))
+}
+
+/// Write `x` into both slots so int and float comparisons see the same threshold
+fn write_const(apls: &mut [Option<Value>], t: &Tunable, x: f64) {
+    let Some(Some(apl)) = apls.get_mut(t.player) else { return };
+    let Some(Value::Object(v)) = apl.pointer_mut(&t.pointer) else { return };
+    v.insert("vfloat".to_string(), serde_json::json!(x));
+    v.insert("vint".to_string(), serde_json::json!(x.round() as i64));
+}
+
+/// Target DPS of iterations 1..=n under `apls`
+pub(crate) fn target_samples(params: &SimParams, apls: &[Option<Value>], seed: u64, iterations: i32) -> Vec<f64> {
+    (1..=iterations.max(0) as u64).map(|idx| {
+        let mut decider = create_team_decider_from_apls(apls, &params.timing);
+        target_dps(&run_single_dps(params, &mut decider, seed, idx), &params.config.target)
+    }).collect()
+}
+
+fn score(objective: Objective, samples: &[f64]) -> f64 {
+    match objective {
+        Objective::Mean => samples.iter().sum::<f64>() / samples.len().max(1) as f64,
+        Objective::Percentile(p) => {
+            let mut sketch = QuantileSketch::default();
+            for &x in samples { sketch.push(x); }
+            sketch.percentile(p)
+        }
+    }
+}
+
+/// Bootstrap resamples behind a percentile objective's error bar
+const BOOTSTRAP_RESAMPLES: usize = 200;
+
+/// Objective gain of `after` over `before`, both measured on the same iterations.
+/// A percentile's error comes from resampling iterations, both sides together so
+/// the pairing holds.
+fn objective_gain(objective: Objective, after: &[f64], before: &[f64], seed: u64) -> Estimate {
+    if let Objective::Mean = objective {
+        let mut gain = RunningStats::default();
+        for (a, b) in after.iter().zip(before.iter()) { gain.push(a - b); }
+        return Estimate::from(&gain);
+    }
+
+    let n = after.len().min(before.len());
+    if n == 0 { return Estimate::default(); }
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut gains = RunningStats::default();
+    let (mut a, mut b) = (Vec::with_capacity(n), Vec::with_capacity(n));
+    for _ in 0..BOOTSTRAP_RESAMPLES {
+        a.clear();
+        b.clear();
+        for _ in 0..n {
+            let i = rng.gen_range(0..n);
+            a.push(after[i]);
+            b.push(before[i]);
+        }
+        gains.push(score(objective, &a) - score(objective, &b));
+    }
+    Estimate { mean: score(objective, after) - score(objective, before), std_error: gains.std_dev() }
+}
+
+/// Tune `spec.tunables` of `apls`, starting from the values they hold
+pub fn tune_apl(params: &SimParams, apls: &[Option<Value>], spec: &TuneSpec, seed: u64) -> TuneResult {
+    let mut best_apls = apls.to_vec();
+    let as_int: Vec<bool> = spec.tunables.iter().map(|t| reads_int(apls, t)).collect();
+    let start_values: Vec<f64> = spec.tunables.iter().zip(&as_int).map(|(t, &i)| read_const(apls, t, i).unwrap_or(t.min)).collect();
+    let grids: Vec<Vec<f64>> = spec.tunables.iter().zip(&as_int).map(|(t, &i)| t.grid(i)).collect();
+    let mut values = start_values.clone();
+    let mut best = score(spec.objective, &target_samples(params, &best_apls, seed, spec.iterations));
+    let mut evaluations = 1;
+
+    for _ in 0..spec.rounds {
+        let mut changed = false;
+        for (i, t) in spec.tunables.iter().enumerate() {
+            for &x in &grids[i] {
+                if (x - values[i]).abs() < 1e-9 { continue; }
+                let mut candidate = best_apls.clone();
+                write_const(&mut candidate, t, x);
+                let s = score(spec.objective, &target_samples(params, &candidate, seed, spec.iterations));
+                evaluations += 1;
+                if s > best {
+                    best = s;
+                    best_apls = candidate;
+                    values[i] = x;
+                    changed = true;
+                }
+            }
+        }
+        if !changed { break; }
+    }
+
+    // fresh seeds for an unbiased before / after comparison
+    let validation_seed = iteration_seed(seed, u64::MAX);
+    let before = target_samples(params, apls, validation_seed, spec.iterations);
+    let after = target_samples(params, &best_apls, validation_seed, spec.iterations);
+
+    TuneResult {
+        apls: best_apls,
+        start_values,
+        values,
+        baseline: score(spec.objective, &before),
+        tuned: score(spec.objective, &after),
+        improvement: objective_gain(spec.objective, &after, &before, validation_seed),
+        evaluations,
+    }
+}